@@ -0,0 +1,59 @@
+use std::fmt::{Display, Formatter};
+
+// Why SlurmJobBuilder::try_build rejected a job.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BuildError {
+    EmptyCommand,
+    InvalidMaxRunTime(String),
+    ConflictingMemory,
+    SequentialWithoutArray,
+    EnvironmentWithoutShell(String),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::EmptyCommand => f.write_str("the command must not be empty"),
+            BuildError::InvalidMaxRunTime(time) => write!(
+                f,
+                "invalid max_run_time format, expected MM, MM:SS, HH:MM:SS, D-HH, D-HH:MM or D-HH:MM:SS, got: {}",
+                time
+            ),
+            BuildError::ConflictingMemory => {
+                f.write_str("memory and memory per cpu are mutually exclusive")
+            }
+            BuildError::SequentialWithoutArray => {
+                f.write_str("sequential array elements require an array")
+            }
+            BuildError::EnvironmentWithoutShell(shell) => write!(
+                f,
+                "modules and environment variables need a POSIX shell such as bash, got: {}",
+                shell
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_explains_empty_command() {
+        assert_eq!(
+            BuildError::EmptyCommand.to_string(),
+            "the command must not be empty"
+        );
+    }
+
+    #[test]
+    fn display_includes_the_rejected_time() {
+        assert!(
+            BuildError::InvalidMaxRunTime("soon".to_string())
+                .to_string()
+                .ends_with("got: soon")
+        );
+    }
+}
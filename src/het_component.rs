@@ -0,0 +1,156 @@
+use crate::memory_size::Memory;
+use serde::{Deserialize, Serialize};
+
+// Resources of an additional component of a heterogeneous job, e.g. a GPU
+// task co-scheduled with the CPU tasks described by the job itself. Each
+// component follows an `#SBATCH hetjob` separator in the script; the command
+// addresses the components with `srun --het-group=<index>`, the job itself
+// being component 0.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HetComponent {
+    cpus: usize,
+    memory: Option<Memory>,
+    nodes: Option<usize>,
+    ntasks: Option<usize>,
+    gres: Option<String>,
+    partition: Option<String>,
+    constraint: Option<String>,
+}
+
+impl Default for HetComponent {
+    fn default() -> HetComponent {
+        HetComponent::new()
+    }
+}
+
+impl HetComponent {
+    pub fn new() -> HetComponent {
+        HetComponent {
+            cpus: 1,
+            memory: None,
+            nodes: None,
+            ntasks: None,
+            gres: None,
+            partition: None,
+            constraint: None,
+        }
+    }
+
+    pub fn set_cpus(mut self, cpus: usize) -> HetComponent {
+        self.cpus = cpus;
+        self
+    }
+
+    // per node, like SlurmJobBuilder::set_memory
+    pub fn set_memory(mut self, memory: Memory) -> HetComponent {
+        self.memory = Some(memory);
+        self
+    }
+
+    pub fn set_nodes(mut self, nodes: usize) -> HetComponent {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn set_ntasks(mut self, ntasks: usize) -> HetComponent {
+        self.ntasks = Some(ntasks);
+        self
+    }
+
+    pub fn set_gres(mut self, gres: String) -> HetComponent {
+        self.gres = Some(gres);
+        self
+    }
+
+    // components may run on a different partition than the job itself
+    pub fn set_partition(mut self, partition: String) -> HetComponent {
+        self.partition = Some(partition);
+        self
+    }
+
+    pub fn set_constraint(mut self, constraint: String) -> HetComponent {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    pub(crate) fn total_cpus(&self) -> usize {
+        self.cpus.saturating_mul(self.ntasks.unwrap_or(1))
+    }
+
+    pub(crate) fn requests_memory(&self) -> bool {
+        self.memory.is_some()
+    }
+
+    // without a memory request the component counts default_megabytes per
+    // node
+    pub(crate) fn total_memory_megabytes(&self, default_megabytes: u64) -> u64 {
+        self.memory
+            .as_ref()
+            .map_or(default_megabytes, Memory::as_megabytes)
+            .saturating_mul(self.nodes.unwrap_or(1) as u64)
+    }
+
+    // the separator followed by the directives of this component
+    pub(crate) fn generate_directives(&self) -> String {
+        let mut ret = String::from("#SBATCH hetjob\n");
+        if let Some(ref partition) = self.partition {
+            ret += format!("#SBATCH --partition={}\n", partition).as_str();
+        }
+        if let Some(nodes) = self.nodes {
+            ret += format!("#SBATCH --nodes={}\n", nodes).as_str();
+        }
+        if let Some(ntasks) = self.ntasks {
+            ret += format!("#SBATCH --ntasks={}\n", ntasks).as_str();
+        }
+        ret += format!("#SBATCH --cpus-per-task={}\n", self.cpus).as_str();
+        if let Some(ref gres) = self.gres {
+            ret += format!("#SBATCH --gres={}\n", gres).as_str();
+        }
+        if let Some(ref constraint) = self.constraint {
+            ret += format!("#SBATCH --constraint=\"{}\"\n", constraint).as_str();
+        }
+        if let Some(ref memory) = self.memory {
+            ret += format!("#SBATCH --mem={}\n", memory).as_str();
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_start_with_separator() {
+        assert_eq!(
+            HetComponent::new().generate_directives(),
+            "#SBATCH hetjob\n#SBATCH --cpus-per-task=1\n"
+        );
+    }
+
+    #[test]
+    fn directives_include_all_settings() {
+        let component = HetComponent::new()
+            .set_partition("gpu".to_string())
+            .set_nodes(1)
+            .set_ntasks(2)
+            .set_cpus(4)
+            .set_gres("gpu:a100:1".to_string())
+            .set_constraint("a100".to_string())
+            .set_memory(Memory::GigaByte(32));
+        assert_eq!(
+            component.generate_directives(),
+            "#SBATCH hetjob\n#SBATCH --partition=gpu\n#SBATCH --nodes=1\n#SBATCH --ntasks=2\n\
+             #SBATCH --cpus-per-task=4\n#SBATCH --gres=gpu:a100:1\n#SBATCH --constraint=\"a100\"\n\
+             #SBATCH --mem=32G\n"
+        );
+    }
+
+    #[test]
+    fn totals_without_memory() {
+        let component = HetComponent::new().set_cpus(4).set_ntasks(3);
+        assert_eq!(component.total_cpus(), 12);
+        assert_eq!(component.total_memory_megabytes(0), 0);
+        assert_eq!(component.set_nodes(2).total_memory_megabytes(100), 200);
+    }
+}
@@ -1,84 +1,352 @@
-use crate::job_post_processing::SlurmJobPostProcessing;
+use crate::het_component::HetComponent;
+use crate::job_handle::JobHandle;
+use crate::job_post_processing::{PostProcessingContext, SlurmJobPostProcessing};
+use crate::job_state::SlurmJobState;
 use crate::job_status::SlurmJobStatus;
+use crate::mail_type::MailType;
 use crate::memory_size::Memory;
+use chrono::{DateTime, Local};
+use log::warn;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
-#[derive(Clone)]
+const DISCARDED_LOG: &str = "/dev/null";
+
+// Post-processing cannot be serialized, a deserialized job checks nothing.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SlurmJob {
     pub(crate) id: String,
+    // the id came from SlurmJobBuilder::set_id, it is kept when the job is rerun
+    pub(crate) custom_id: bool,
     pub(crate) number: Option<i32>,
+    pub(crate) handle: Option<JobHandle>,
+    pub(crate) dependencies: Vec<JobHandle>,
+    pub(crate) after_ok: Vec<i32>,
     pub(crate) command: String,
     pub(crate) working_directory: Option<String>,
-    #[allow(unused)]
+    pub(crate) chdir: bool,
     pub(crate) env: HashMap<String, String>,
-    #[allow(unused)]
+    pub(crate) modules: Vec<String>,
+    pub(crate) name: Option<String>,
     pub(crate) description: String,
     pub(crate) status: SlurmJobStatus,
-    pub(crate) max_run_time: Option<String>, // D-HH:MM:SS
+    pub(crate) slurm_state: Option<SlurmJobState>,
+    pub(crate) exit_code: Option<String>,
+    pub(crate) failed_submissions: u32,
+    // consecutive polls in which squeue did not list the job
+    pub(crate) absent_polls: u32,
+    // why squeue reports the job as pending, e.g. "Priority" or "Resources"
+    pub(crate) pending_reason: Option<String>,
+    pub(crate) retry_after: Option<DateTime<Local>>,
+    pub(crate) submitted_at: Option<DateTime<Local>>,
+    pub(crate) started_at: Option<DateTime<Local>>,
+    pub(crate) finished_at: Option<DateTime<Local>>,
+    pub(crate) max_run_time: Option<String>, // e.g. D-HH:MM:SS, see set_max_run_time
+    pub(crate) partition: Option<String>,
+    pub(crate) account: Option<String>,
+    pub(crate) qos: Option<String>,
+    pub(crate) reservation: Option<String>,
+    pub(crate) comment: Option<String>,
+    pub(crate) wckey: Option<String>,
+    pub(crate) requeue: Option<bool>,
+    pub(crate) begin: Option<String>,
+    pub(crate) deadline: Option<String>,
+    pub(crate) nice: Option<i32>,
+    pub(crate) signal: Option<String>,
+    pub(crate) held: bool,
+    pub(crate) priority: i32,
     pub(crate) output_file: Option<String>,
     pub(crate) error_file: Option<String>,
+    pub(crate) input_file: Option<String>,
+    #[serde(skip, default = "SlurmJobPostProcessing::do_nothing")]
     pub(crate) on_finished: SlurmJobPostProcessing,
-    pub(crate) memory: Memory,
+    // None leaves it to the partition default
+    pub(crate) memory: Option<Memory>,
+    pub(crate) memory_per_cpu: Option<Memory>,
+    pub(crate) tmp_disk: Option<Memory>,
     pub(crate) cpus: usize,
+    pub(crate) gres: Option<String>,
+    pub(crate) mail_user: Option<String>,
+    pub(crate) mail_types: Vec<MailType>,
+    pub(crate) array: Option<String>,
+    pub(crate) nodes: Option<usize>,
+    pub(crate) ntasks: Option<usize>,
+    pub(crate) ntasks_per_node: Option<usize>,
+    pub(crate) threads_per_core: Option<usize>,
+    pub(crate) nodelist: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    pub(crate) constraint: Option<String>,
+    pub(crate) licenses: Option<String>,
+    pub(crate) cpu_freq: Option<String>,
+    pub(crate) exclusive: bool,
+    pub(crate) raw_directives: Vec<String>,
+    pub(crate) het_components: Vec<HetComponent>,
+    pub(crate) shell: String,
+    pub(crate) preamble: Option<String>,
+    pub(crate) epilogue: Option<String>,
+    pub(crate) timestamp_markers: bool,
 }
 
 impl Display for SlurmJob {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(format!("{}", self.id).as_str())
+        f.write_str(self.id.as_str())
     }
 }
 
 impl SlurmJob {
+    // an empty command would "finish" successfully without doing anything
     #[allow(unused)]
     pub fn new(
         command: String,
         description: String,
         on_finished: SlurmJobPostProcessing,
     ) -> SlurmJob {
+        assert!(!command.trim().is_empty(), "the command must not be empty");
         SlurmJob {
             id: Uuid::new_v4().to_string(),
+            custom_id: false,
             number: None,
+            handle: None,
+            dependencies: Vec::new(),
+            after_ok: Vec::new(),
             command,
             working_directory: None,
+            chdir: false,
             env: HashMap::new(),
+            modules: Vec::new(),
+            name: None,
             description,
             status: SlurmJobStatus::CREATED,
+            slurm_state: None,
+            exit_code: None,
+            failed_submissions: 0,
+            absent_polls: 0,
+            pending_reason: None,
+            retry_after: None,
+            submitted_at: None,
+            started_at: None,
+            finished_at: None,
             max_run_time: None,
+            partition: None,
+            account: None,
+            qos: None,
+            reservation: None,
+            comment: None,
+            wckey: None,
+            requeue: None,
+            begin: None,
+            deadline: None,
+            nice: None,
+            signal: None,
+            held: false,
+            priority: 0,
             output_file: None,
             error_file: None,
+            input_file: None,
             on_finished,
-            memory: Memory::MegaByte(100),
+            memory: None,
+            memory_per_cpu: None,
+            tmp_disk: None,
             cpus: 1,
+            gres: None,
+            mail_user: None,
+            mail_types: Vec::new(),
+            array: None,
+            nodes: None,
+            ntasks: None,
+            ntasks_per_node: None,
+            threads_per_core: None,
+            nodelist: Vec::new(),
+            exclude: Vec::new(),
+            constraint: None,
+            licenses: None,
+            cpu_freq: None,
+            exclusive: false,
+            raw_directives: Vec::new(),
+            het_components: Vec::new(),
+            shell: String::from("/bin/bash"),
+            preamble: None,
+            epilogue: None,
+            timestamp_markers: true,
         }
     }
 
-    pub(crate) fn get_status(&self) -> SlurmJobStatus {
+    pub fn status(&self) -> SlurmJobStatus {
         self.status.clone()
     }
 
+    // when sbatch accepted the job, None while it was not submitted
+    pub fn submitted_at(&self) -> Option<DateTime<Local>> {
+        self.submitted_at
+    }
+
+    // when the manager first saw the job running in squeue, None for jobs
+    // that finished between two checks
+    pub fn started_at(&self) -> Option<DateTime<Local>> {
+        self.started_at
+    }
+
+    // the reason squeue gave for not starting the job at the last check
+    // (without parentheses), None unless the job is pending
+    pub fn pending_reason(&self) -> Option<&str> {
+        self.pending_reason.as_deref()
+    }
+
+    // when the manager noticed the job had finished, crashed or was cancelled
+    pub fn finished_at(&self) -> Option<DateTime<Local>> {
+        self.finished_at
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    // free-form metadata such as a sample id, never passed to SLURM
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    // final state reported by sacct, None until the job left the queue
+    pub fn slurm_state(&self) -> Option<&SlurmJobState> {
+        self.slurm_state.as_ref()
+    }
+
+    // exit code reported by sacct in the form <code>:<signal>
+    pub fn exit_code(&self) -> Option<&str> {
+        self.exit_code.as_deref()
+    }
+
     #[allow(unused)]
     pub(crate) fn get_id(&self) -> &String {
         &self.id
     }
 
+    // the name passed to --job-name, e.g. "align-sample42-1b9d6bcd". A uuid
+    // is shortened to its first block, a custom id is used as a whole since
+    // ids such as sample-42a and sample-42b only differ at the end.
+    pub(crate) fn job_name(&self) -> String {
+        let suffix = if self.custom_id {
+            self.id.clone()
+        } else {
+            self.id.chars().take(8).collect()
+        };
+        match self.name {
+            Some(ref name) => format!("{}-{}", name, suffix),
+            None => self.id.clone(),
+        }
+    }
+
+    // replaces the %-symbols SLURM fills in for the whole job, zero-padded
+    // as in %4j. None if the pattern has a symbol that is not known here.
+    fn expand_log_pattern(&self, pattern: &str) -> Option<String> {
+        let mut expanded = String::new();
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            let mut width = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                width.push(digit);
+            }
+            match chars.next() {
+                Some(symbol) => {
+                    expanded += &self.expand_log_symbol(symbol, width.parse().unwrap_or(0))?
+                }
+                None => {
+                    expanded.push('%');
+                    expanded += &width;
+                }
+            }
+        }
+        Some(expanded)
+    }
+
+    // %j of an array job, %a and the node, step, task and user symbols
+    // differ per array task or are only known on the cluster
+    fn expand_log_symbol(&self, symbol: char, width: usize) -> Option<String> {
+        let number = |number: i32| format!("{:0width$}", number, width = width);
+        match symbol {
+            '%' => Some(String::from("%")),
+            'x' => Some(self.job_name()),
+            'j' if self.array.is_none() => self.number.map(number),
+            'A' => self.number.map(number),
+            _ => None,
+        }
+    }
+
+    // SLURM resolves relative log paths against the working directory of the
+    // job, which is the --chdir directory or else the directory sbatch ran
+    // in, i.e. the current directory of the manager for a local backend
+    fn resolve_log_path(&self, path: &str) -> Option<PathBuf> {
+        let resolved = PathBuf::from(self.expand_log_pattern(path)?);
+        if resolved.is_absolute() {
+            return Some(resolved);
+        }
+        if let Some(ref working_directory) = self.working_directory
+            && self.uses_chdir()
+        {
+            return Some(Path::new(working_directory).join(resolved));
+        }
+        match std::env::current_dir() {
+            Ok(current_dir) => Some(current_dir.join(resolved)),
+            Err(_) => Some(resolved),
+        }
+    }
+
+    // unset log files are discarded
+    fn log_file(path: &Option<String>) -> &str {
+        path.as_deref().unwrap_or(DISCARDED_LOG)
+    }
+
+    // jobs without explicit log files write them to dir, named after their
+    // SLURM job number
+    pub(crate) fn assign_log_files(&mut self, dir: &Path) {
+        if self.output_file.is_none() {
+            self.output_file = Some(dir.join("%j.out").display().to_string());
+        }
+        if self.error_file.is_none() {
+            self.error_file = Some(dir.join("%j.err").display().to_string());
+        }
+    }
+
+    pub(crate) fn post_processing_context(&self) -> PostProcessingContext {
+        PostProcessingContext {
+            job_id: self.id.clone(),
+            number: self.number,
+            output_file: self.resolve_log_path(Self::log_file(&self.output_file)),
+            error_file: self.resolve_log_path(Self::log_file(&self.error_file)),
+            exit_code: self.exit_code.clone(),
+        }
+    }
+
     pub(crate) fn run_post_processing(&self) -> SlurmJobStatus {
-        if self.on_finished.check() {
+        if self.on_finished.check(&self.post_processing_context()) {
             SlurmJobStatus::FINISHED
         } else {
             SlurmJobStatus::CRASHED
         }
     }
 
-    pub(crate) fn set_number(&mut self, number: i32) -> () {
-        match self.number {
-            Some(_) => panic!("must not overwrite existing job number"),
-            None => self.number = Some(number),
+    // a job that is submitted again (or re-attached after SLURM reused its
+    // number) takes the latest number sbatch reported
+    pub(crate) fn set_number(&mut self, number: i32) {
+        if let Some(previous) = self.number
+            && previous != number
+        {
+            warn!(
+                job_id = self.id.as_str(), job_number = number, previous_job_number = previous;
+                "job {} had number {} and is now tracked as {}", self, previous, number
+            );
         }
+        self.number = Some(number);
     }
 
-    pub(crate) fn set_status(&mut self, status: SlurmJobStatus) -> () {
+    pub(crate) fn set_status(&mut self, status: SlurmJobStatus) {
         self.status = status;
     }
 
@@ -87,51 +355,367 @@ impl SlurmJob {
         self.number.expect("no number set for the job")
     }
 
+    // wraps the value in single quotes so the shell takes it literally
+    pub(crate) fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+
+    // whether the script wrapper (pushd, module load, export, echo) can run in
+    // the job's interpreter
+    pub(crate) fn has_posix_shell(&self) -> bool {
+        Self::is_posix_shell(&self.shell)
+    }
+
+    // the interpreter is the first word of the shebang, e.g. bash in
+    // "/bin/bash -l", or the first word after the options of env as in
+    // "/usr/bin/env -S bash -e"
+    pub(crate) fn is_posix_shell(shell: &str) -> bool {
+        let mut names = shell
+            .split_whitespace()
+            .map(|word| word.rsplit('/').next().unwrap_or_default());
+        let mut interpreter = names.next().unwrap_or_default();
+        if interpreter == "env" {
+            interpreter = names
+                .find(|name| !name.starts_with('-') && !name.contains('='))
+                .unwrap_or_default();
+        }
+        matches!(interpreter, "sh" | "bash" | "zsh" | "ksh" | "dash")
+    }
+
+    // forgets everything about the last run, the job is submitted as a new
+    // SLURM job under a new id unless the caller chose the id
+    pub(crate) fn reset_for_rerun(&mut self) {
+        if !self.custom_id {
+            self.id = Uuid::new_v4().to_string();
+        }
+        self.number = None;
+        self.after_ok = Vec::new();
+        self.slurm_state = None;
+        self.exit_code = None;
+        self.failed_submissions = 0;
+        self.absent_polls = 0;
+        self.pending_reason = None;
+        self.retry_after = None;
+        self.submitted_at = None;
+        self.started_at = None;
+        self.finished_at = None;
+    }
+
+    // merges manager-wide modules and variables into the job, they are
+    // loaded before the job's own modules and the job's own value wins for
+    // variables set on both
+    pub(crate) fn apply_environment(&mut self, modules: &[String], env: &HashMap<String, String>) {
+        let mut merged: Vec<String> = modules
+            .iter()
+            .filter(|module| !self.modules.contains(module))
+            .cloned()
+            .collect();
+        merged.append(&mut self.modules);
+        self.modules = merged;
+        for (key, value) in env {
+            self.env.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    pub(crate) fn generate_module_loads(&self) -> String {
+        self.modules
+            .iter()
+            .map(|module| format!("module load {}\n", module))
+            .collect()
+    }
+
+    pub(crate) fn generate_env_exports(&self) -> String {
+        let mut keys: Vec<&String> = self.env.keys().collect();
+        keys.sort();
+        keys.iter()
+            .map(|key| format!("export {}={}\n", key, Self::shell_quote(&self.env[*key])))
+            .collect()
+    }
+
+    // tasks of the job, --ntasks-per-node applies to every node and wins if
+    // it asks for more than --ntasks
+    fn tasks(&self) -> usize {
+        let per_node = self
+            .ntasks_per_node
+            .map_or(1, |ntasks| ntasks.saturating_mul(self.nodes.unwrap_or(1)));
+        self.ntasks.unwrap_or(1).max(per_node)
+    }
+
+    // array tasks that may run at the same time, the %limit caps the number
+    // of indices
+    fn array_tasks(&self) -> usize {
+        let Some(ref array) = self.array else {
+            return 1;
+        };
+        let (ranges, limit) = match array.split_once('%') {
+            Some((ranges, limit)) => (ranges, limit.parse::<usize>().ok()),
+            None => (array.as_str(), None),
+        };
+        let indices = ranges
+            .split(',')
+            .map(Self::array_range_len)
+            .fold(0, usize::saturating_add);
+        limit.map_or(indices, |limit| indices.min(limit)).max(1)
+    }
+
+    // number of indices in a range such as "7", "0-99" or "1-10:2"
+    fn array_range_len(range: &str) -> usize {
+        let (bounds, step) = match range.split_once(':') {
+            Some((bounds, step)) => (bounds, step.parse::<usize>().unwrap_or(1).max(1)),
+            None => (range, 1),
+        };
+        match bounds.split_once('-') {
+            Some((start, end)) => match (start.parse::<usize>(), end.parse::<usize>()) {
+                (Ok(start), Ok(end)) if start <= end => (end - start) / step + 1,
+                _ => 1,
+            },
+            None => 1,
+        }
+    }
+
+    // CPUs the job allocates in total over all its tasks and array tasks
+    pub(crate) fn total_cpus(&self) -> usize {
+        self.het_components
+            .iter()
+            .map(HetComponent::total_cpus)
+            .fold(
+                self.cpus
+                    .saturating_mul(self.tasks())
+                    .saturating_mul(self.array_tasks()),
+                usize::saturating_add,
+            )
+    }
+
+    // false if the job or one of its het components leaves the memory to the
+    // partition default
+    pub(crate) fn requests_memory(&self) -> bool {
+        (self.memory.is_some() || self.memory_per_cpu.is_some())
+            && self
+                .het_components
+                .iter()
+                .all(HetComponent::requests_memory)
+    }
+
+    // megabytes the job allocates in total, --mem applies to every node and
+    // array task. Without a memory request default_megabytes are counted per
+    // node.
+    pub(crate) fn total_memory_megabytes(&self, default_megabytes: u64) -> u64 {
+        let own = match self.memory_per_cpu {
+            Some(ref memory_per_cpu) => memory_per_cpu
+                .as_megabytes()
+                .saturating_mul(self.cpus.saturating_mul(self.tasks()) as u64),
+            None => self
+                .memory
+                .as_ref()
+                .map_or(default_megabytes, Memory::as_megabytes)
+                .saturating_mul(self.nodes.unwrap_or(1) as u64),
+        };
+        self.het_components
+            .iter()
+            .map(|component| component.total_memory_megabytes(default_megabytes))
+            .fold(
+                own.saturating_mul(self.array_tasks() as u64),
+                u64::saturating_add,
+            )
+    }
+
+    // without a shell there is no pushd, SLURM changes the directory then
+    fn uses_chdir(&self) -> bool {
+        self.chdir || !self.has_posix_shell()
+    }
+
     pub(crate) fn generate_slurm_commands(&self) -> String {
+        let pushd = self
+            .working_directory
+            .as_ref()
+            .filter(|_| !self.uses_chdir());
         let mut ret = String::new();
-        match self.working_directory {
-            Some(ref working_directory) => {
-                ret += format!("pushd {}\n", working_directory).as_str();
-            }
-            None => {}
+        if let Some(working_directory) = pushd {
+            ret += format!("pushd {}\n", working_directory).as_str();
         }
         ret += self.command.as_str();
         ret += "\n";
-        if self.working_directory.is_some() {
+        if pushd.is_some() {
             ret += "popd\n";
         }
         ret
     }
 
-    pub(crate) fn generate_slurm_script(&self) -> String {
-        let mut ret = String::from("#!/bin/bash\n");
-        ret += format!("#SBATCH --job-name={}\n", self.id).as_str();
-        match self.output_file {
-            Some(ref output_file) => {
-                ret += format!("#SBATCH --output={}\n", output_file).as_str();
-            }
-            None => {}
+    fn generate_identity_directives(&self) -> String {
+        let mut ret = format!("#SBATCH --job-name={}\n", self.job_name());
+        if let Some(ref account) = self.account {
+            ret += format!("#SBATCH --account={}\n", account).as_str();
         }
-        match self.error_file {
-            Some(ref error_file) => {
-                ret += format!("#SBATCH --error={}\n", error_file).as_str();
-            }
-            None => {}
+        if let Some(ref partition) = self.partition {
+            ret += format!("#SBATCH --partition={}\n", partition).as_str();
+        }
+        if let Some(ref qos) = self.qos {
+            ret += format!("#SBATCH --qos={}\n", qos).as_str();
+        }
+        if let Some(ref comment) = self.comment {
+            ret += format!("#SBATCH --comment=\"{}\"\n", comment).as_str();
+        }
+        if let Some(ref wckey) = self.wckey {
+            ret += format!("#SBATCH --wckey={}\n", wckey).as_str();
+        }
+        if let Some(ref mail_user) = self.mail_user {
+            ret += format!("#SBATCH --mail-user={}\n", mail_user).as_str();
+        }
+        if !self.mail_types.is_empty() {
+            let types: Vec<String> = self.mail_types.iter().map(|t| t.to_string()).collect();
+            ret += format!("#SBATCH --mail-type={}\n", types.join(",")).as_str();
+        }
+        ret
+    }
+
+    fn generate_io_directives(&self) -> String {
+        let mut ret = String::new();
+        ret += format!("#SBATCH --output={}\n", Self::log_file(&self.output_file)).as_str();
+        ret += format!("#SBATCH --error={}\n", Self::log_file(&self.error_file)).as_str();
+        if let Some(ref input_file) = self.input_file {
+            ret += format!("#SBATCH --input={}\n", input_file).as_str();
+        }
+        if let Some(ref working_directory) = self.working_directory
+            && self.uses_chdir()
+        {
+            ret += format!("#SBATCH --chdir={}\n", working_directory).as_str();
+        }
+        ret
+    }
+
+    fn generate_resource_directives(&self) -> String {
+        let mut ret = String::new();
+        if let Some(nodes) = self.nodes {
+            ret += format!("#SBATCH --nodes={}\n", nodes).as_str();
+        }
+        if let Some(ntasks) = self.ntasks {
+            ret += format!("#SBATCH --ntasks={}\n", ntasks).as_str();
+        }
+        if let Some(ntasks_per_node) = self.ntasks_per_node {
+            ret += format!("#SBATCH --ntasks-per-node={}\n", ntasks_per_node).as_str();
         }
         ret += format!("#SBATCH --cpus-per-task={}\n", self.cpus).as_str();
-        ret += match self.memory {
-            Memory::MegaByte(memory) => format!("#SBATCH --mem={}M\n", memory),
-            Memory::GigaByte(memory) => format!("#SBATCH --mem={}G\n", memory),
+        if let Some(threads_per_core) = self.threads_per_core {
+            ret += format!("#SBATCH --threads-per-core={}\n", threads_per_core).as_str();
+        }
+        if let Some(ref gres) = self.gres {
+            ret += format!("#SBATCH --gres={}\n", gres).as_str();
+        }
+        if !self.nodelist.is_empty() {
+            ret += format!("#SBATCH --nodelist={}\n", self.nodelist.join(",")).as_str();
         }
-        .as_str();
-        match &self.max_run_time {
-            Some(max_run_time) => ret += format!("#SBATCH --time={}\n", max_run_time).as_str(),
+        if !self.exclude.is_empty() {
+            ret += format!("#SBATCH --exclude={}\n", self.exclude.join(",")).as_str();
+        }
+        if self.exclusive {
+            ret += "#SBATCH --exclusive\n";
+        }
+        if let Some(ref constraint) = self.constraint {
+            ret += format!("#SBATCH --constraint=\"{}\"\n", constraint).as_str();
+        }
+        if let Some(ref licenses) = self.licenses {
+            ret += format!("#SBATCH --licenses={}\n", licenses).as_str();
+        }
+        if let Some(ref cpu_freq) = self.cpu_freq {
+            ret += format!("#SBATCH --cpu-freq={}\n", cpu_freq).as_str();
+        }
+        if let Some(ref memory_per_cpu) = self.memory_per_cpu {
+            ret += format!("#SBATCH --mem-per-cpu={}\n", memory_per_cpu).as_str();
+        } else if let Some(ref memory) = self.memory {
+            ret += format!("#SBATCH --mem={}\n", memory).as_str();
+        }
+        if let Some(ref tmp_disk) = self.tmp_disk {
+            ret += format!("#SBATCH --tmp={}\n", tmp_disk).as_str();
+        }
+        if let Some(ref max_run_time) = self.max_run_time {
+            ret += format!("#SBATCH --time={}\n", max_run_time).as_str();
+        }
+        ret
+    }
+
+    fn generate_scheduling_directives(&self) -> String {
+        let mut ret = String::new();
+        if let Some(ref reservation) = self.reservation {
+            ret += format!("#SBATCH --reservation={}\n", reservation).as_str();
+        }
+        if let Some(ref begin) = self.begin {
+            ret += format!("#SBATCH --begin={}\n", begin).as_str();
+        }
+        if let Some(ref deadline) = self.deadline {
+            ret += format!("#SBATCH --deadline={}\n", deadline).as_str();
+        }
+        if let Some(nice) = self.nice {
+            ret += format!("#SBATCH --nice={}\n", nice).as_str();
+        }
+        if let Some(ref signal) = self.signal {
+            ret += format!("#SBATCH --signal={}\n", signal).as_str();
+        }
+        if self.held {
+            ret += "#SBATCH --hold\n";
+        }
+        match self.requeue {
+            Some(true) => ret += "#SBATCH --requeue\n",
+            Some(false) => ret += "#SBATCH --no-requeue\n",
             None => {}
         }
+        if let Some(ref array) = self.array {
+            ret += format!("#SBATCH --array={}\n", array).as_str();
+        }
+        if !self.after_ok.is_empty() {
+            let numbers: Vec<String> = self.after_ok.iter().map(|n| n.to_string()).collect();
+            ret += format!("#SBATCH --dependency=afterok:{}\n", numbers.join(":")).as_str();
+        }
+        ret
+    }
+
+    // the script schedule_job would submit, dependencies are only filled in
+    // once the job is submitted
+    pub fn to_script_string(&self) -> String {
+        self.generate_slurm_script()
+    }
+
+    pub(crate) fn generate_slurm_script(&self) -> String {
+        let mut ret = format!("#!{}\n", self.shell);
+        ret += self.generate_identity_directives().as_str();
+        ret += self.generate_io_directives().as_str();
+        ret += self.generate_resource_directives().as_str();
+        ret += self.generate_scheduling_directives().as_str();
+        for directive in &self.raw_directives {
+            ret += format!("#SBATCH {}\n", directive).as_str();
+        }
+        for component in &self.het_components {
+            ret += component.generate_directives().as_str();
+        }
+        if !self.has_posix_shell() {
+            ret += "\n";
+            ret += Self::as_block(&self.preamble).as_str();
+            ret += self.command.as_str();
+            ret += "\n";
+            ret += Self::as_block(&self.epilogue).as_str();
+            return ret;
+        }
         ret += "\n\n";
-        ret += "echo START: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        ret += Self::as_block(&self.preamble).as_str();
+        ret += self.generate_module_loads().as_str();
+        ret += self.generate_env_exports().as_str();
+        if self.timestamp_markers {
+            ret += "echo START: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        }
         ret += self.generate_slurm_commands().as_str();
-        ret += "\necho END: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        ret += "\n";
+        if self.timestamp_markers {
+            ret += "echo END: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        }
+        ret += Self::as_block(&self.epilogue).as_str();
         ret
     }
+
+    // preamble and epilogue are copied verbatim and end with a newline
+    fn as_block(text: &Option<String>) -> String {
+        match text {
+            Some(text) if !text.ends_with('\n') => format!("{}\n", text),
+            Some(text) => text.clone(),
+            None => String::new(),
+        }
+    }
 }
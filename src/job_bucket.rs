@@ -0,0 +1,9 @@
+// Where a SlurmManager currently keeps a job: open jobs wait to be submitted,
+// scheduled jobs are in SLURM's queue and finished jobs are done, whatever
+// their outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum JobBucket {
+    Open,
+    Scheduled,
+    Finished,
+}
@@ -1,43 +1,163 @@
-//todo: add capability to add modules on startup
-
+use crate::build_error::BuildError;
+use crate::het_component::HetComponent;
 use crate::job::SlurmJob;
+use crate::job_defaults::SlurmDefaults;
 use crate::job_post_processing::SlurmJobPostProcessing;
 use crate::job_status::SlurmJobStatus;
+use crate::mail_type::MailType;
 use crate::memory_size::Memory;
 use std::collections::HashMap;
 use uuid::Uuid;
 
 pub struct SlurmJobBuilder {
     command: String,
+    id: Option<String>,
     working_directory: Option<String>,
+    chdir: bool,
     env: HashMap<String, String>,
+    modules: Vec<String>,
+    name: Option<String>,
     description: String,
     max_run_time: Option<String>,
+    partition: Option<String>,
+    account: Option<String>,
+    qos: Option<String>,
+    reservation: Option<String>,
+    comment: Option<String>,
+    wckey: Option<String>,
+    requeue: Option<bool>,
+    begin: Option<String>,
+    deadline: Option<String>,
+    nice: Option<i32>,
+    signal: Option<String>,
+    held: bool,
+    priority: i32,
     output_file: Option<String>,
     error_file: Option<String>,
+    input_file: Option<String>,
     on_finished: SlurmJobPostProcessing,
-    memory: Memory,
+    memory: Option<Memory>,
+    memory_per_cpu: Option<Memory>,
+    tmp_disk: Option<Memory>,
+    default_memory: Option<Memory>,
     cpus: usize,
+    gres: Option<String>,
+    mail_user: Option<String>,
+    mail_types: Vec<MailType>,
+    array: Option<String>,
+    array_sequential: bool,
+    nodes: Option<usize>,
+    ntasks: Option<usize>,
+    ntasks_per_node: Option<usize>,
+    threads_per_core: Option<usize>,
+    nodelist: Vec<String>,
+    exclude: Vec<String>,
+    constraint: Option<String>,
+    licenses: Option<String>,
+    cpu_freq: Option<String>,
+    exclusive: bool,
+    raw_directives: Vec<String>,
+    het_components: Vec<HetComponent>,
+    shell: String,
+    preamble: Option<String>,
+    epilogue: Option<String>,
+    timestamp_markers: bool,
 }
 
 impl SlurmJobBuilder {
     pub fn new(command: String) -> SlurmJobBuilder {
         SlurmJobBuilder {
             command,
+            id: None,
             working_directory: None,
+            chdir: false,
             env: HashMap::new(),
+            modules: Vec::new(),
+            name: None,
             description: String::from(""),
             max_run_time: None,
-            output_file: Some("/dev/null".to_string()),
-            error_file: Some("/dev/null".to_string()),
+            partition: None,
+            account: None,
+            qos: None,
+            reservation: None,
+            comment: None,
+            wckey: None,
+            requeue: None,
+            begin: None,
+            deadline: None,
+            nice: None,
+            signal: None,
+            held: false,
+            priority: 0,
+            output_file: None,
+            error_file: None,
+            input_file: None,
             on_finished: SlurmJobPostProcessing::do_nothing(),
-            memory: Memory::MegaByte(100),
+            memory: None,
+            memory_per_cpu: None,
+            tmp_disk: None,
+            default_memory: None,
             cpus: 1,
+            gres: None,
+            mail_user: None,
+            mail_types: Vec::new(),
+            array: None,
+            array_sequential: false,
+            nodes: None,
+            ntasks: None,
+            ntasks_per_node: None,
+            threads_per_core: None,
+            nodelist: Vec::new(),
+            exclude: Vec::new(),
+            constraint: None,
+            licenses: None,
+            cpu_freq: None,
+            exclusive: false,
+            raw_directives: Vec::new(),
+            het_components: Vec::new(),
+            shell: String::from("/bin/bash"),
+            preamble: None,
+            epilogue: None,
+            timestamp_markers: true,
         }
     }
 
+    // Starts from site-wide defaults, any setter called afterwards overrides
+    // them. Default memory is ignored if the job sets memory per cpu.
+    pub fn from_defaults(command: String, defaults: &SlurmDefaults) -> SlurmJobBuilder {
+        let mut builder = SlurmJobBuilder::new(command);
+        builder.account = defaults.account.clone();
+        builder.partition = defaults.partition.clone();
+        builder.qos = defaults.qos.clone();
+        builder.default_memory = defaults.memory.clone();
+        // Not routed through set_max_run_time, a malformed site default is
+        // reported by try_build instead of panicking here.
+        builder.max_run_time = defaults.time.clone();
+        builder
+    }
+
+    // --mem and --mem-per-cpu are mutually exclusive in SLURM.
     pub fn set_memory(mut self, memory: Memory) -> SlurmJobBuilder {
-        self.memory = memory;
+        assert!(
+            self.memory_per_cpu.is_none(),
+            "memory and memory per cpu are mutually exclusive"
+        );
+        self.memory = Some(memory);
+        self
+    }
+
+    pub fn set_memory_per_cpu(mut self, memory: Memory) -> SlurmJobBuilder {
+        assert!(
+            self.memory.is_none(),
+            "memory and memory per cpu are mutually exclusive"
+        );
+        self.memory_per_cpu = Some(memory);
+        self
+    }
+
+    // Local scratch disk per node, separate from the memory request.
+    pub fn set_tmp_disk(mut self, tmp_disk: Memory) -> SlurmJobBuilder {
+        self.tmp_disk = Some(tmp_disk);
         self
     }
 
@@ -46,56 +166,240 @@ impl SlurmJobBuilder {
         self
     }
 
-    #[allow(unused)]
+    // Let SLURM change into the working directory with --chdir instead of
+    // wrapping the command in pushd/popd.
+    pub fn set_chdir(mut self, chdir: bool) -> SlurmJobBuilder {
+        self.chdir = chdir;
+        self
+    }
+
+    // Exported before the command runs, the value is passed literally.
     pub fn add_env(mut self, key: String, value: String) -> SlurmJobBuilder {
+        assert!(
+            Self::check_env_key(&key),
+            "invalid environment variable name: {}",
+            key
+        );
         self.env.insert(key, value);
         self
     }
 
+    // Shell variable names consist of letters, digits and underscores and
+    // must not start with a digit.
+    pub(crate) fn check_env_key(key: &str) -> bool {
+        match key.chars().next() {
+            Some(first) if first.is_ascii_alphabetic() || first == '_' => {
+                key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    }
+
+    // Modules are loaded in the order they were added before the command runs.
+    pub fn add_module(mut self, module: String) -> SlurmJobBuilder {
+        self.modules.push(module);
+        self
+    }
+
+    pub fn set_modules(mut self, modules: Vec<String>) -> SlurmJobBuilder {
+        self.modules = modules;
+        self
+    }
+
+    // Replaces the generated uuid, e.g. with the key of the job in another
+    // system. The id names the kept script file and, without a name, the
+    // SLURM job, so only letters, digits, '-', '_' and '.' are allowed.
+    // Keeping ids unique is up to the caller.
+    pub fn set_id(mut self, id: String) -> SlurmJobBuilder {
+        assert!(Self::check_id(&id), "invalid job id: {:?}", id);
+        self.id = Some(id);
+        self
+    }
+
+    fn check_id(id: &str) -> bool {
+        !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    // The name shows up in squeue, a short uuid fragment is appended to keep it unique.
+    // It ends up unquoted in --job-name=, so it must not contain whitespace.
+    pub fn set_name(mut self, name: String) -> SlurmJobBuilder {
+        assert!(
+            !name.is_empty() && !name.contains(char::is_whitespace),
+            "name must not be empty or contain whitespace, got: {:?}",
+            name
+        );
+        self.name = Some(name);
+        self
+    }
+
     #[allow(unused)]
     pub fn set_description(mut self, desc: String) -> SlurmJobBuilder {
         self.description = desc;
         self
     }
 
-    // Validates the SLURM time format D-HH:MM:SS required by --time.
-    fn check_max_runtime_pattern(pattern: &str) -> bool {
-        let parts: Vec<&str> = pattern.splitn(2, '-').collect();
-        if parts.len() != 2 {
-            return false;
+    // Validates the time formats accepted by --time: MM, MM:SS, HH:MM:SS,
+    // D-HH, D-HH:MM and D-HH:MM:SS.
+    pub(crate) fn check_max_runtime_pattern(pattern: &str) -> bool {
+        match pattern.split_once('-') {
+            Some((days, clock)) => {
+                Self::parse_digits(days).is_some() && Self::check_clock(clock, true)
+            }
+            None => Self::check_clock(pattern, false),
         }
-        if parts[0].parse::<u32>().is_err() {
-            return false;
+    }
+
+    // With a day prefix the clock starts with hours (below 24), without one
+    // it starts with minutes unless all three fields are given. The leading
+    // field is otherwise unbounded, later fields must be below 60.
+    fn check_clock(clock: &str, with_days: bool) -> bool {
+        let fields: Option<Vec<u32>> = clock.split(':').map(Self::parse_digits).collect();
+        match fields.as_deref() {
+            Some([first, rest @ ..]) if rest.len() <= 2 => {
+                (!with_days || *first < 24) && rest.iter().all(|field| *field < 60)
+            }
+            _ => false,
         }
-        let hms: Vec<&str> = parts[1].split(':').collect();
-        if hms.len() != 3 {
-            return false;
+    }
+
+    fn parse_digits(field: &str) -> Option<u32> {
+        if field.is_empty() || !field.chars().all(|c| c.is_ascii_digit()) {
+            return None;
         }
-        let hours: u32 = match hms[0].parse() {
-            Ok(v) => v,
-            Err(_) => return false,
-        };
-        let minutes: u32 = match hms[1].parse() {
-            Ok(v) => v,
-            Err(_) => return false,
+        field.parse().ok()
+    }
+
+    pub fn set_max_run_time(self, max_run_time: String) -> SlurmJobBuilder {
+        self.try_set_max_run_time(max_run_time)
+            .unwrap_or_else(|why| panic!("{}", why))
+    }
+
+    // Like set_max_run_time, but returns the error for a malformed time,
+    // e.g. one typed in by a user.
+    pub fn try_set_max_run_time(
+        mut self,
+        max_run_time: String,
+    ) -> Result<SlurmJobBuilder, BuildError> {
+        if !Self::check_max_runtime_pattern(&max_run_time) {
+            return Err(BuildError::InvalidMaxRunTime(max_run_time));
+        }
+        self.max_run_time = Some(max_run_time);
+        Ok(self)
+    }
+
+    // Partition names are site-specific, so any value is passed through to SLURM.
+    pub fn set_partition(mut self, partition: String) -> SlurmJobBuilder {
+        self.partition = Some(partition);
+        self
+    }
+
+    pub fn set_account(mut self, account: String) -> SlurmJobBuilder {
+        self.account = Some(account);
+        self
+    }
+
+    pub fn set_qos(mut self, qos: String) -> SlurmJobBuilder {
+        self.qos = Some(qos);
+        self
+    }
+
+    // Free text for accounting tools, e.g. a pipeline run id. It is quoted in
+    // the script, so it may contain spaces but no double quotes or newlines.
+    pub fn set_comment(mut self, comment: String) -> SlurmJobBuilder {
+        assert!(
+            !comment.contains(['"', '\n']),
+            "comment must not contain double quotes or newlines, got: {}",
+            comment
+        );
+        self.comment = Some(comment);
+        self
+    }
+
+    pub fn set_wckey(mut self, wckey: String) -> SlurmJobBuilder {
+        self.wckey = Some(wckey);
+        self
+    }
+
+    pub fn set_reservation(mut self, reservation: String) -> SlurmJobBuilder {
+        self.reservation = Some(reservation);
+        self
+    }
+
+    // Whether SLURM may requeue the job after a node failure, left to the cluster default if unset.
+    pub fn set_requeue(mut self, requeue: bool) -> SlurmJobBuilder {
+        self.requeue = Some(requeue);
+        self
+    }
+
+    // Delays the start, SLURM time specs such as `now+1hour`, `midnight` or
+    // `2024-01-01T02:00:00` are passed through verbatim.
+    pub fn set_begin(mut self, begin: String) -> SlurmJobBuilder {
+        self.begin = Some(begin);
+        self
+    }
+
+    // SLURM removes the job if it cannot finish by this time, passed through verbatim.
+    pub fn set_deadline(mut self, deadline: String) -> SlurmJobBuilder {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    // Positive values lower the priority of the job, negative ones raise it
+    // where the QOS allows it.
+    pub fn set_nice(mut self, nice: i32) -> SlurmJobBuilder {
+        self.nice = Some(nice);
+        self
+    }
+
+    // Validates --signal specs of the form [{R|B}:]<sig_num>[@sig_time], where
+    // the signal is a number or a name such as USR1 or SIGTERM.
+    fn check_signal_pattern(pattern: &str) -> bool {
+        let (flags, signal) = match pattern.split_once(':') {
+            Some((flags, signal)) => (flags, signal),
+            None => ("", pattern),
         };
-        let seconds: u32 = match hms[2].parse() {
-            Ok(v) => v,
-            Err(_) => return false,
+        let (signal, time) = match signal.split_once('@') {
+            Some((signal, time)) => (signal, Some(time)),
+            None => (signal, None),
         };
-        hours < 24 && minutes < 60 && seconds < 60
+        let flags_valid = matches!(flags, "" | "R" | "B" | "RB" | "BR");
+        let signal_valid = !signal.is_empty() && signal.chars().all(|c| c.is_ascii_alphanumeric());
+        flags_valid && signal_valid && time.is_none_or(|time| Self::parse_digits(time).is_some())
     }
 
-    pub fn set_max_run_time(mut self, max_run_time: String) -> SlurmJobBuilder {
+    // Asks SLURM to send a signal before the time limit, e.g. B:USR1@120
+    // signals the batch shell two minutes early to let it checkpoint.
+    pub fn set_signal(mut self, signal: String) -> SlurmJobBuilder {
         assert!(
-            Self::check_max_runtime_pattern(&max_run_time),
-            "invalid max_run_time format, expected D-HH:MM:SS, got: {}",
-            max_run_time
+            Self::check_signal_pattern(&signal),
+            "invalid signal format, expected e.g. USR1, B:USR1@120 or 10@60, got: {}",
+            signal
         );
-        self.max_run_time = Some(max_run_time);
+        self.signal = Some(signal);
+        self
+    }
+
+    // Submits the job held, it only becomes eligible to run once
+    // SlurmManager::release_job is called for it.
+    pub fn set_held(mut self, held: bool) -> SlurmJobBuilder {
+        self.held = held;
+        self
+    }
+
+    // The manager submits open jobs with a higher priority first, jobs of
+    // equal priority in the order they were added. Defaults to 0, this is
+    // independent of SLURM's own priority (see set_nice).
+    pub fn set_priority(mut self, priority: i32) -> SlurmJobBuilder {
+        self.priority = priority;
         self
     }
 
+    // Output and error are discarded (/dev/null) unless set here or the
+    // manager assigns log files (see SlurmManager::set_log_dir).
     pub fn set_output_file(mut self, output_file: String) -> SlurmJobBuilder {
         self.output_file = Some(output_file);
         self
@@ -106,6 +410,52 @@ impl SlurmJobBuilder {
         self
     }
 
+    // Validates the replacement symbols SLURM accepts in --output/--error
+    // file names, e.g. %j (job number), %x (job name), %A and %a (array job
+    // and task), optionally zero-padded as in %4a. %% is a literal percent.
+    fn check_filename_pattern(pattern: &str) -> bool {
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                continue;
+            }
+            while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+            if !chars
+                .next()
+                .is_some_and(|symbol| "%AabJjNnstux".contains(symbol))
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // Like set_output_file, but rejects unknown %-symbols that SLURM would
+    // otherwise silently write into the file name.
+    pub fn set_output_pattern(self, pattern: String) -> SlurmJobBuilder {
+        assert!(
+            Self::check_filename_pattern(&pattern),
+            "invalid file name pattern, expected symbols such as %j, %x, %A or %a, got: {}",
+            pattern
+        );
+        self.set_output_file(pattern)
+    }
+
+    pub fn set_error_pattern(self, pattern: String) -> SlurmJobBuilder {
+        assert!(
+            Self::check_filename_pattern(&pattern),
+            "invalid file name pattern, expected symbols such as %j, %x, %A or %a, got: {}",
+            pattern
+        );
+        self.set_error_file(pattern)
+    }
+
+    // File connected to the command's stdin, SLURM uses /dev/null if unset.
+    pub fn set_input_file(mut self, input_file: String) -> SlurmJobBuilder {
+        self.input_file = Some(input_file);
+        self
+    }
+
     pub fn set_on_finished(mut self, finished: SlurmJobPostProcessing) -> SlurmJobBuilder {
         self.on_finished = finished;
         self
@@ -116,21 +466,285 @@ impl SlurmJobBuilder {
         self
     }
 
+    pub fn set_nodes(mut self, nodes: usize) -> SlurmJobBuilder {
+        assert!(nodes > 0, "nodes must be at least 1");
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn set_ntasks(mut self, ntasks: usize) -> SlurmJobBuilder {
+        assert!(ntasks > 0, "ntasks must be at least 1");
+        self.ntasks = Some(ntasks);
+        self
+    }
+
+    // Tasks started on every node, e.g. one MPI rank per socket; combined
+    // with set_nodes for hybrid MPI/OpenMP layouts.
+    pub fn set_ntasks_per_node(mut self, ntasks_per_node: usize) -> SlurmJobBuilder {
+        assert!(ntasks_per_node > 0, "ntasks per node must be at least 1");
+        self.ntasks_per_node = Some(ntasks_per_node);
+        self
+    }
+
+    // 1 disables hyperthreading, the job only gets one hardware thread per
+    // core, e.g. for memory-bandwidth-bound codes.
+    pub fn set_threads_per_core(mut self, threads_per_core: usize) -> SlurmJobBuilder {
+        assert!(threads_per_core > 0, "threads per core must be at least 1");
+        self.threads_per_core = Some(threads_per_core);
+        self
+    }
+
+    // Node names such as `node01` or ranges such as `node[01-04]`.
+    pub fn set_nodelist(mut self, nodes: Vec<String>) -> SlurmJobBuilder {
+        self.nodelist = nodes;
+        self
+    }
+
+    pub fn set_exclude(mut self, nodes: Vec<String>) -> SlurmJobBuilder {
+        self.exclude = nodes;
+        self
+    }
+
+    // Node features are passed through verbatim so SLURM's `&` and `|` operators can be used.
+    pub fn set_constraint(mut self, constraint: String) -> SlurmJobBuilder {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    // Floating licenses the job needs, e.g. "ansys:2" or "matlab:1,comsol:1".
+    // SLURM keeps the job pending until they are available.
+    pub fn set_licenses(mut self, licenses: String) -> SlurmJobBuilder {
+        self.licenses = Some(licenses);
+        self
+    }
+
+    // CPU frequency or governor, e.g. "Performance", "2400000" (kHz) or
+    // "Low-High:OnDemand", passed through verbatim.
+    pub fn set_cpu_freq(mut self, cpu_freq: String) -> SlurmJobBuilder {
+        self.cpu_freq = Some(cpu_freq);
+        self
+    }
+
+    // Reserves whole nodes for the job, e.g. for reproducible benchmarks.
+    pub fn set_exclusive(mut self, exclusive: bool) -> SlurmJobBuilder {
+        self.exclusive = exclusive;
+        self
+    }
+
+    // Generic resources such as `gpu:2` or `gpu:a100:4` are passed through verbatim.
+    pub fn set_gres(mut self, gres: String) -> SlurmJobBuilder {
+        self.gres = Some(gres);
+        self
+    }
+
+    pub fn set_mail_user(mut self, mail_user: String) -> SlurmJobBuilder {
+        self.mail_user = Some(mail_user);
+        self
+    }
+
+    pub fn set_mail_type(mut self, mail_types: Vec<MailType>) -> SlurmJobBuilder {
+        self.mail_types = mail_types;
+        self
+    }
+
+    // Validates array specs such as "0-99", "1,3,5", "1-10:2" or "0-15%4": a
+    // comma separated list of indices or ranges with an optional step and an
+    // optional limit of simultaneously running tasks at the end.
+    fn check_array_pattern(pattern: &str) -> bool {
+        let (ranges, limit) = match pattern.split_once('%') {
+            Some((ranges, limit)) => (ranges, Some(limit)),
+            None => (pattern, None),
+        };
+        if limit.is_some_and(|limit| Self::parse_digits(limit).is_none()) {
+            return false;
+        }
+        ranges.split(',').all(|range| {
+            let (bounds, step) = match range.split_once(':') {
+                Some((bounds, step)) => (bounds, Some(step)),
+                None => (range, None),
+            };
+            let bounds_valid = match bounds.split_once('-') {
+                Some((start, end)) => matches!(
+                    (Self::parse_digits(start), Self::parse_digits(end)),
+                    (Some(start), Some(end)) if start <= end
+                ),
+                None => Self::parse_digits(bounds).is_some(),
+            };
+            bounds_valid && step.is_none_or(|step| Self::parse_digits(step).is_some_and(|s| s > 0))
+        })
+    }
+
+    pub fn set_array(mut self, array: String) -> SlurmJobBuilder {
+        assert!(
+            Self::check_array_pattern(&array),
+            "invalid array format, expected e.g. 0-99, 1-10:2 or 0-15%4, got: {}",
+            array
+        );
+        self.array = Some(array);
+        self
+    }
+
+    // Runs the array elements one after the other in index order (%1), e.g.
+    // for checkpointed arrays. SLURM has no dependency between elements of
+    // the same array, so an element still starts if the previous one failed.
+    pub fn set_array_sequential(mut self, array_sequential: bool) -> SlurmJobBuilder {
+        self.array_sequential = array_sequential;
+        self
+    }
+
+    // Escape hatch for options without a typed setter, e.g. "--switches=1".
+    // The string is emitted verbatim after the known directives.
+    pub fn add_raw_directive(mut self, directive: String) -> SlurmJobBuilder {
+        self.raw_directives.push(directive);
+        self
+    }
+
+    // Turns the job into a heterogeneous job: the resources set on the
+    // builder form component 0, every added component is co-scheduled with
+    // it in its own `#SBATCH hetjob` block.
+    pub fn add_het_component(mut self, component: HetComponent) -> SlurmJobBuilder {
+        self.het_components.push(component);
+        self
+    }
+
+    // The interpreter for the shebang line, e.g. "/bin/zsh" or "/usr/bin/env python3".
+    // For interpreters other than sh, bash, zsh, ksh or dash the command is
+    // written as is, without START/END markers. try_build rejects such a job
+    // if it loads modules or sets environment variables.
+    pub fn set_shell(mut self, shell: String) -> SlurmJobBuilder {
+        self.shell = shell;
+        self
+    }
+
+    // Lines inserted right after the #SBATCH block, before modules are loaded,
+    // e.g. "set -euo pipefail" or sourcing a site environment script.
+    pub fn set_preamble(mut self, preamble: String) -> SlurmJobBuilder {
+        self.preamble = Some(preamble);
+        self
+    }
+
+    // Whether the script echoes START and END timestamps around the command,
+    // true by default. Turn them off to keep the output file to the command's
+    // own output.
+    pub fn set_timestamp_markers(mut self, timestamp_markers: bool) -> SlurmJobBuilder {
+        self.timestamp_markers = timestamp_markers;
+        self
+    }
+
+    // Lines appended after the command and its END marker, e.g. to clean up
+    // scratch space.
+    pub fn set_epilogue(mut self, epilogue: String) -> SlurmJobBuilder {
+        self.epilogue = Some(epilogue);
+        self
+    }
+
+    // Panics on the errors try_build reports.
     pub fn build(&self) -> SlurmJob {
+        self.try_build()
+            .unwrap_or_else(|why| panic!("invalid job: {}", why))
+    }
+
+    // Checks the invariants the setters cannot enforce on their own, e.g. a
+    // command that was never filled in, settings that only conflict once the
+    // job is complete, or values from SlurmDefaults that bypass the setters.
+    pub fn try_build(&self) -> Result<SlurmJob, BuildError> {
+        if self.command.trim().is_empty() {
+            return Err(BuildError::EmptyCommand);
+        }
+        if let Some(ref max_run_time) = self.max_run_time
+            && !Self::check_max_runtime_pattern(max_run_time)
+        {
+            return Err(BuildError::InvalidMaxRunTime(max_run_time.clone()));
+        }
+        if self.memory.is_some() && self.memory_per_cpu.is_some() {
+            return Err(BuildError::ConflictingMemory);
+        }
+        if self.array_sequential && self.array.is_none() {
+            return Err(BuildError::SequentialWithoutArray);
+        }
+        let sets_environment = !self.env.is_empty() || !self.modules.is_empty();
+        if sets_environment && !SlurmJob::is_posix_shell(&self.shell) {
+            return Err(BuildError::EnvironmentWithoutShell(self.shell.clone()));
+        }
+        Ok(self.assemble())
+    }
+
+    fn assemble(&self) -> SlurmJob {
         SlurmJob {
-            id: Uuid::new_v4().to_string(),
+            id: self
+                .id
+                .clone()
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            custom_id: self.id.is_some(),
             number: None,
+            handle: None,
+            dependencies: Vec::new(),
+            after_ok: Vec::new(),
             command: self.command.clone(),
             working_directory: self.working_directory.clone(),
+            chdir: self.chdir,
             env: self.env.clone(),
+            modules: self.modules.clone(),
+            name: self.name.clone(),
             description: self.description.clone(),
             status: SlurmJobStatus::CREATED,
+            slurm_state: None,
+            exit_code: None,
+            failed_submissions: 0,
+            absent_polls: 0,
+            pending_reason: None,
+            retry_after: None,
+            submitted_at: None,
+            started_at: None,
+            finished_at: None,
             max_run_time: self.max_run_time.clone(),
+            partition: self.partition.clone(),
+            account: self.account.clone(),
+            qos: self.qos.clone(),
+            reservation: self.reservation.clone(),
+            comment: self.comment.clone(),
+            wckey: self.wckey.clone(),
+            requeue: self.requeue,
+            begin: self.begin.clone(),
+            deadline: self.deadline.clone(),
+            nice: self.nice,
+            signal: self.signal.clone(),
+            held: self.held,
+            priority: self.priority,
             output_file: self.output_file.clone(),
             error_file: self.error_file.clone(),
+            input_file: self.input_file.clone(),
             on_finished: self.on_finished.clone(),
-            memory: self.memory.clone(),
+            memory: self.memory.clone().or_else(|| self.default_memory.clone()),
+            memory_per_cpu: self.memory_per_cpu.clone(),
+            tmp_disk: self.tmp_disk.clone(),
             cpus: self.cpus,
+            gres: self.gres.clone(),
+            mail_user: self.mail_user.clone(),
+            mail_types: self.mail_types.clone(),
+            array: match self.array {
+                // a limit of one replaces any other limit
+                Some(ref array) if self.array_sequential => {
+                    Some(format!("{}%1", array.split('%').next().unwrap_or_default()))
+                }
+                _ => self.array.clone(),
+            },
+            nodes: self.nodes,
+            ntasks: self.ntasks,
+            ntasks_per_node: self.ntasks_per_node,
+            threads_per_core: self.threads_per_core,
+            nodelist: self.nodelist.clone(),
+            exclude: self.exclude.clone(),
+            constraint: self.constraint.clone(),
+            licenses: self.licenses.clone(),
+            cpu_freq: self.cpu_freq.clone(),
+            exclusive: self.exclusive,
+            raw_directives: self.raw_directives.clone(),
+            het_components: self.het_components.clone(),
+            shell: self.shell.clone(),
+            preamble: self.preamble.clone(),
+            epilogue: self.epilogue.clone(),
+            timestamp_markers: self.timestamp_markers,
         }
     }
 }
@@ -149,17 +763,1137 @@ mod tests {
         assert!(SlurmJobBuilder::check_max_runtime_pattern("3-12:30:59"));
     }
 
+    #[test]
+    fn max_runtime_pattern_valid_without_days() {
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("90"));
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("90:30"));
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("00:05:00"));
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("36:00:00"));
+    }
+
+    #[test]
+    fn max_runtime_pattern_valid_with_partial_clock() {
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("2-12"));
+        assert!(SlurmJobBuilder::check_max_runtime_pattern("2-12:30"));
+    }
+
     #[test]
     fn max_runtime_pattern_invalid_format() {
-        assert!(!SlurmJobBuilder::check_max_runtime_pattern("00:05:00"));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern(""));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern("1:2:3:4"));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern("05:60"));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern("1-"));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern("-1:00"));
+        assert!(!SlurmJobBuilder::check_max_runtime_pattern("+5"));
         assert!(!SlurmJobBuilder::check_max_runtime_pattern("not-a-time"));
         assert!(!SlurmJobBuilder::check_max_runtime_pattern("1-25:00:00"));
         assert!(!SlurmJobBuilder::check_max_runtime_pattern("1-00:60:00"));
     }
 
     #[test]
-    #[should_panic(expected = "invalid max_run_time format")]
-    fn set_max_run_time_panics_on_bad_input() {
-        SlurmJobBuilder::new("sleep 1".to_string()).set_max_run_time("badformat".to_string());
+    #[should_panic(expected = "mutually exclusive")]
+    fn set_memory_per_cpu_after_memory_panics() {
+        SlurmJobBuilder::new("sleep 1".to_string())
+            .set_memory(Memory::GigaByte(1))
+            .set_memory_per_cpu(Memory::MegaByte(512));
+    }
+
+    #[test]
+    #[should_panic(expected = "mutually exclusive")]
+    fn set_memory_after_memory_per_cpu_panics() {
+        SlurmJobBuilder::new("sleep 1".to_string())
+            .set_memory_per_cpu(Memory::MegaByte(512))
+            .set_memory(Memory::GigaByte(1));
+    }
+
+    #[test]
+    fn try_build_rejects_conflicting_memory() {
+        let mut builder = SlurmJobBuilder::new("sleep 1".to_string());
+        builder.memory = Some(Memory::GigaByte(1));
+        builder.memory_per_cpu = Some(Memory::MegaByte(512));
+        assert_eq!(
+            builder.try_build().err(),
+            Some(BuildError::ConflictingMemory)
+        );
+    }
+
+    #[test]
+    fn set_id_replaces_the_uuid() {
+        let job = SlurmJobBuilder::new("sleep 1".to_string())
+            .set_id("sample-42_run.3".to_string())
+            .build();
+        assert_eq!(job.get_id(), "sample-42_run.3");
+    }
+
+    #[test]
+    fn job_name_uses_the_whole_custom_id() {
+        let named = |id: &str| {
+            SlurmJobBuilder::new("sleep 1".to_string())
+                .set_name("align".to_string())
+                .set_id(id.to_string())
+                .build()
+                .job_name()
+        };
+        assert_eq!(named("sample-42a"), "align-sample-42a");
+        assert_ne!(named("sample-42a"), named("sample-42b"));
+    }
+
+    #[test]
+    fn id_defaults_to_a_uuid() {
+        let job = SlurmJobBuilder::new("sleep 1".to_string()).build();
+        assert!(Uuid::parse_str(job.get_id()).is_ok());
+    }
+
+    #[test]
+    fn id_invalid() {
+        assert!(!SlurmJobBuilder::check_id(""));
+        assert!(!SlurmJobBuilder::check_id("../etc"));
+        assert!(!SlurmJobBuilder::check_id("a b"));
+    }
+
+    #[test]
+    fn env_key_valid() {
+        assert!(SlurmJobBuilder::check_env_key("PATH"));
+        assert!(SlurmJobBuilder::check_env_key("_MY_VAR2"));
+    }
+
+    #[test]
+    fn env_key_invalid() {
+        assert!(!SlurmJobBuilder::check_env_key(""));
+        assert!(!SlurmJobBuilder::check_env_key("2FAST"));
+        assert!(!SlurmJobBuilder::check_env_key("MY-VAR"));
+        assert!(!SlurmJobBuilder::check_env_key("A B"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid environment variable name")]
+    fn add_env_panics_on_bad_key() {
+        SlurmJobBuilder::new("sleep 1".to_string()).add_env("A=B".to_string(), "c".to_string());
+    }
+
+    #[test]
+    fn array_pattern_valid_ranges() {
+        assert!(SlurmJobBuilder::check_array_pattern("0-99"));
+        assert!(SlurmJobBuilder::check_array_pattern("1-10:2"));
+        assert!(SlurmJobBuilder::check_array_pattern("7"));
+    }
+
+    #[test]
+    fn array_pattern_valid_lists_and_limits() {
+        assert!(SlurmJobBuilder::check_array_pattern("1,3,5-7"));
+        assert!(SlurmJobBuilder::check_array_pattern("0-15%4"));
+    }
+
+    #[test]
+    fn array_pattern_invalid() {
+        assert!(!SlurmJobBuilder::check_array_pattern(""));
+        assert!(!SlurmJobBuilder::check_array_pattern("10-1"));
+        assert!(!SlurmJobBuilder::check_array_pattern("1-10:0"));
+        assert!(!SlurmJobBuilder::check_array_pattern("0-15%"));
+        assert!(!SlurmJobBuilder::check_array_pattern("a-b"));
+        assert!(!SlurmJobBuilder::check_array_pattern("1,,2"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid array format")]
+    fn set_array_panics_on_bad_input() {
+        SlurmJobBuilder::new("sleep 1".to_string()).set_array("1-".to_string());
+    }
+
+    #[test]
+    fn signal_pattern_valid_names_and_numbers() {
+        assert!(SlurmJobBuilder::check_signal_pattern("USR1"));
+        assert!(SlurmJobBuilder::check_signal_pattern("10@60"));
+        assert!(SlurmJobBuilder::check_signal_pattern("SIGTERM@300"));
+    }
+
+    #[test]
+    fn signal_pattern_valid_with_flags() {
+        assert!(SlurmJobBuilder::check_signal_pattern("B:USR1@120"));
+        assert!(SlurmJobBuilder::check_signal_pattern("R:TERM"));
+        assert!(SlurmJobBuilder::check_signal_pattern("RB:USR2@30"));
+    }
+
+    #[test]
+    fn signal_pattern_invalid() {
+        assert!(!SlurmJobBuilder::check_signal_pattern(""));
+        assert!(!SlurmJobBuilder::check_signal_pattern("B:"));
+        assert!(!SlurmJobBuilder::check_signal_pattern("X:USR1"));
+        assert!(!SlurmJobBuilder::check_signal_pattern("USR1@"));
+        assert!(!SlurmJobBuilder::check_signal_pattern("USR1@2m"));
+        assert!(!SlurmJobBuilder::check_signal_pattern("USR 1"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid signal format")]
+    fn set_signal_panics_on_bad_input() {
+        SlurmJobBuilder::new("sleep 1".to_string()).set_signal("B:USR1@soon".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "comment must not contain")]
+    fn set_comment_panics_on_newline() {
+        SlurmJobBuilder::new("sleep 1".to_string())
+            .set_comment("run 42\n#SBATCH --qos=high".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "name must not be empty or contain whitespace")]
+    fn set_name_panics_on_newline() {
+        SlurmJobBuilder::new("sleep 1".to_string())
+            .set_name("align\n#SBATCH --qos=high".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "name must not be empty or contain whitespace")]
+    fn set_name_panics_on_space() {
+        SlurmJobBuilder::new("sleep 1".to_string()).set_name("align sample".to_string());
+    }
+
+    #[test]
+    fn filename_pattern_valid_symbols() {
+        assert!(SlurmJobBuilder::check_filename_pattern("logs/%x-%j.out"));
+        assert!(SlurmJobBuilder::check_filename_pattern("%A_%a.err"));
+        assert!(SlurmJobBuilder::check_filename_pattern("plain.log"));
+    }
+
+    #[test]
+    fn filename_pattern_valid_padding_and_literal_percent() {
+        assert!(SlurmJobBuilder::check_filename_pattern("task-%4a.out"));
+        assert!(SlurmJobBuilder::check_filename_pattern("100%%-done.log"));
+    }
+
+    #[test]
+    fn filename_pattern_invalid() {
+        assert!(!SlurmJobBuilder::check_filename_pattern("%q.out"));
+        assert!(!SlurmJobBuilder::check_filename_pattern("out%"));
+        assert!(!SlurmJobBuilder::check_filename_pattern("%12"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid file name pattern")]
+    fn set_error_pattern_panics_on_unknown_symbol() {
+        SlurmJobBuilder::new("sleep 1".to_string()).set_error_pattern("%z.err".to_string());
+    }
+
+    #[test]
+    fn try_build_accepts_valid_job() {
+        let job = SlurmJobBuilder::new("sleep 1".to_string())
+            .set_max_run_time("10:00".to_string())
+            .set_memory(Memory::GigaByte(1))
+            .try_build()
+            .expect("valid job");
+        assert_eq!(job.command(), "sleep 1");
+    }
+
+    #[test]
+    fn try_build_accepts_memory_per_cpu_with_default_memory() {
+        let mut builder =
+            SlurmJobBuilder::new("sleep 1".to_string()).set_memory_per_cpu(Memory::MegaByte(512));
+        builder.default_memory = Some(Memory::GigaByte(4));
+        assert!(builder.try_build().is_ok());
+    }
+
+    #[test]
+    fn try_build_rejects_empty_command() {
+        assert_eq!(
+            SlurmJobBuilder::new("  \n".to_string()).try_build().err(),
+            Some(BuildError::EmptyCommand)
+        );
+    }
+
+    #[test]
+    fn sequential_without_array_is_rejected() {
+        let builder = SlurmJobBuilder::new("sleep 1".to_string()).set_array_sequential(true);
+        assert_eq!(
+            builder.try_build().err(),
+            Some(BuildError::SequentialWithoutArray)
+        );
+    }
+
+    #[test]
+    fn try_build_rejects_environment_without_posix_shell() {
+        let built = SlurmJobBuilder::new("print(1)".to_string())
+            .set_shell("/usr/bin/env python3".to_string())
+            .add_env("OMP_NUM_THREADS".to_string(), "4".to_string())
+            .try_build();
+        assert_eq!(
+            built.err(),
+            Some(BuildError::EnvironmentWithoutShell(
+                "/usr/bin/env python3".to_string()
+            ))
+        );
+        let built = SlurmJobBuilder::new("print(1)".to_string())
+            .set_shell("/usr/bin/python3".to_string())
+            .add_module("python/3.12".to_string())
+            .try_build();
+        assert!(built.is_err());
+    }
+
+    #[test]
+    fn try_build_accepts_environment_with_login_shell() {
+        let job = SlurmJobBuilder::new("sleep 1".to_string())
+            .set_shell("/bin/bash -l".to_string())
+            .add_module("gcc/12".to_string())
+            .try_build()
+            .expect("bash is a POSIX shell");
+        assert!(job.generate_slurm_script().contains("module load gcc/12\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid max_run_time format")]
+    fn set_max_run_time_panics_on_bad_input() {
+        SlurmJobBuilder::new("sleep 1".to_string()).set_max_run_time("badformat".to_string());
+    }
+
+    #[test]
+    fn try_set_max_run_time_accepts_valid_time() {
+        let job = SlurmJobBuilder::new("sleep 1".to_string())
+            .try_set_max_run_time("1-12:00".to_string())
+            .expect("valid time")
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --time=1-12:00\n")
+        );
+    }
+
+    #[test]
+    fn try_set_max_run_time_accepts_minutes() {
+        let built =
+            SlurmJobBuilder::new("sleep 1".to_string()).try_set_max_run_time("90".to_string());
+        assert!(built.is_ok());
+    }
+
+    #[test]
+    fn try_set_max_run_time_rejects_invalid_time() {
+        let built =
+            SlurmJobBuilder::new("sleep 1".to_string()).try_set_max_run_time("soon".to_string());
+        assert_eq!(
+            built.err(),
+            Some(BuildError::InvalidMaxRunTime("soon".to_string()))
+        );
+    }
+
+    #[test]
+    fn try_build_rejects_invalid_default_time() {
+        let defaults = SlurmDefaults {
+            time: Some("soon".to_string()),
+            ..site_defaults()
+        };
+        let built = SlurmJobBuilder::from_defaults("sleep 1".to_string(), &defaults).try_build();
+        assert_eq!(
+            built.err(),
+            Some(BuildError::InvalidMaxRunTime("soon".to_string()))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid job: the command must not be empty")]
+    fn build_panics_on_empty_command() {
+        SlurmJobBuilder::new(String::new()).build();
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid job: the command must not be empty")]
+    fn build_panics_on_whitespace_command() {
+        SlurmJobBuilder::new(" \t\n".to_string())
+            .set_cpus(2)
+            .set_name("upstream-bug".to_string())
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "the command must not be empty")]
+    fn job_new_panics_on_empty_command() {
+        SlurmJob::new(
+            "   ".to_string(),
+            String::new(),
+            SlurmJobPostProcessing::do_nothing(),
+        );
+    }
+
+    #[test]
+    fn try_build_accepts_command_with_surrounding_whitespace() {
+        let job = SlurmJobBuilder::new("  sleep 1\n".to_string()).try_build();
+        assert_eq!(job.expect("valid job").command(), "  sleep 1\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid job: invalid max_run_time format")]
+    fn build_panics_on_bad_default_max_run_time() {
+        let defaults = SlurmDefaults {
+            time: Some("badformat".to_string()),
+            ..site_defaults()
+        };
+        SlurmJobBuilder::from_defaults("sleep 1".to_string(), &defaults).build();
+    }
+
+    fn site_defaults() -> SlurmDefaults {
+        SlurmDefaults {
+            account: Some("proj42".to_string()),
+            partition: Some("main".to_string()),
+            qos: Some("normal".to_string()),
+            memory: Some(Memory::GigaByte(4)),
+            time: Some("02:00:00".to_string()),
+        }
+    }
+
+    #[test]
+    fn from_defaults_applies_site_settings() {
+        let job = SlurmJobBuilder::from_defaults("sleep 1".to_string(), &site_defaults()).build();
+        assert_eq!(job.account.as_deref(), Some("proj42"));
+        assert_eq!(job.partition.as_deref(), Some("main"));
+        assert_eq!(job.qos.as_deref(), Some("normal"));
+        assert_eq!(job.memory, Some(Memory::GigaByte(4)));
+        assert_eq!(job.max_run_time.as_deref(), Some("02:00:00"));
+    }
+
+    #[test]
+    fn from_defaults_can_be_overridden() {
+        let job = SlurmJobBuilder::from_defaults("sleep 1".to_string(), &site_defaults())
+            .set_partition("gpu".to_string())
+            .set_memory(Memory::GigaByte(16))
+            .build();
+        assert_eq!(job.partition.as_deref(), Some("gpu"));
+        assert_eq!(job.memory, Some(Memory::GigaByte(16)));
+        assert_eq!(job.account.as_deref(), Some("proj42"));
+    }
+
+    #[test]
+    fn from_defaults_allows_memory_per_cpu() {
+        let job = SlurmJobBuilder::from_defaults("sleep 1".to_string(), &site_defaults())
+            .set_memory_per_cpu(Memory::MegaByte(500))
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --mem-per-cpu=500M\n")
+        );
+        assert!(!job.generate_slurm_script().contains("--mem=4G"));
+    }
+
+    fn sleep_job() -> SlurmJob {
+        SlurmJobBuilder::new(String::from("sleep 5")).build()
+    }
+
+    #[test]
+    fn generate_script_with_partition() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_partition("bigmem".to_string())
+            .build();
+        let expected = format!(
+            "#SBATCH --job-name={}\n#SBATCH --partition=bigmem\n",
+            job.get_id()
+        );
+        assert!(job.generate_slurm_script().contains(&expected));
+    }
+
+    #[test]
+    fn generate_script_without_partition() {
+        let job = sleep_job();
+        assert!(!job.generate_slurm_script().contains("--partition"));
+    }
+
+    #[test]
+    fn generate_script_with_account() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_account("proj42".to_string())
+            .build();
+        let expected = format!(
+            "#SBATCH --job-name={}\n#SBATCH --account=proj42\n",
+            job.get_id()
+        );
+        assert!(job.generate_slurm_script().contains(&expected));
+    }
+
+    #[test]
+    fn generate_script_without_account() {
+        let job = sleep_job();
+        assert!(!job.generate_slurm_script().contains("--account"));
+    }
+
+    #[test]
+    fn generate_script_with_gres() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_gres("gpu:2".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --gres=gpu:2\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_typed_gres() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_gres("gpu:a100:4".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --gres=gpu:a100:4\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_gres() {
+        let job = sleep_job();
+        assert!(!job.generate_slurm_script().contains("--gres"));
+    }
+
+    #[test]
+    fn generate_script_with_mail_settings() {
+        use crate::mail_type::MailType::{BEGIN, END};
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_mail_user("me@example.org".to_string())
+            .set_mail_type(vec![BEGIN, END])
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --mail-user=me@example.org\n"));
+        assert!(script.contains("#SBATCH --mail-type=BEGIN,END\n"));
+    }
+
+    #[test]
+    fn generate_script_with_single_mail_type() {
+        use crate::mail_type::MailType::FAIL;
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_mail_type(vec![FAIL])
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --mail-type=FAIL\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_mail_settings() {
+        let script = sleep_job().generate_slurm_script();
+        assert!(!script.contains("--mail-user"));
+        assert!(!script.contains("--mail-type"));
+    }
+
+    #[test]
+    fn generate_script_with_nodelist() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nodelist(vec!["node01".to_string(), "node02".to_string()])
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --nodelist=node01,node02\n"));
+        assert!(!script.contains("--exclude"));
+    }
+
+    #[test]
+    fn generate_script_with_exclude() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_exclude(vec!["node05".to_string()])
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --exclude=node05\n"));
+        assert!(!script.contains("--nodelist"));
+    }
+
+    #[test]
+    fn generate_script_with_empty_node_lists() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nodelist(Vec::new())
+            .set_exclude(Vec::new())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(!script.contains("--nodelist"));
+        assert!(!script.contains("--exclude"));
+    }
+
+    #[test]
+    fn generate_script_with_constraint() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_constraint("avx512&infiniband".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --constraint=\"avx512&infiniband\"\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_constraint() {
+        assert!(!sleep_job().generate_slurm_script().contains("--constraint"));
+    }
+
+    #[test]
+    fn generate_script_with_qos() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_qos("high".to_string())
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --qos=high\n"));
+    }
+
+    #[test]
+    fn generate_script_with_qos_account_and_partition() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_account("proj42".to_string())
+            .set_partition("gpu".to_string())
+            .set_qos("long".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script().contains(
+                "#SBATCH --account=proj42\n#SBATCH --partition=gpu\n#SBATCH --qos=long\n"
+            )
+        );
+    }
+
+    #[test]
+    fn generate_script_without_qos() {
+        assert!(!sleep_job().generate_slurm_script().contains("--qos"));
+    }
+
+    #[test]
+    fn generate_script_with_reservation() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_reservation("deadline_crunch".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --reservation=deadline_crunch\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_reservation() {
+        assert!(
+            !sleep_job()
+                .generate_slurm_script()
+                .contains("--reservation")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_raw_directives_in_order() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_array("0-3".to_string())
+            .add_raw_directive("--switches=1".to_string())
+            .add_raw_directive("--signal=B:USR1@120".to_string())
+            .build();
+        assert!(job.generate_slurm_script().contains(
+            "#SBATCH --array=0-3\n#SBATCH --switches=1\n#SBATCH --signal=B:USR1@120\n\n"
+        ));
+    }
+
+    #[test]
+    fn generate_script_with_single_raw_directive() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_raw_directive("--exclusive".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --exclusive\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_requeue() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_requeue(true)
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --requeue\n"));
+        assert!(!script.contains("--no-requeue"));
+    }
+
+    #[test]
+    fn generate_script_with_no_requeue() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_requeue(false)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --no-requeue\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_requeue_setting() {
+        assert!(!sleep_job().generate_slurm_script().contains("requeue"));
+    }
+
+    #[test]
+    fn generate_script_with_relative_begin() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_begin("now+1hour".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --begin=now+1hour\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_absolute_begin() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_begin("2024-01-01T02:00:00".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --begin=2024-01-01T02:00:00\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_begin() {
+        assert!(!sleep_job().generate_slurm_script().contains("--begin"));
+    }
+
+    #[test]
+    fn generate_script_with_deadline_and_time() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_max_run_time("02:00:00".to_string())
+            .set_deadline("2024-06-01T08:00:00".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --time=02:00:00\n"));
+        assert!(script.contains("#SBATCH --deadline=2024-06-01T08:00:00\n"));
+    }
+
+    #[test]
+    fn generate_script_with_relative_deadline() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_deadline("now+6hours".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --deadline=now+6hours\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_deadline() {
+        assert!(!sleep_job().generate_slurm_script().contains("--deadline"));
+    }
+
+    #[test]
+    fn generate_script_with_positive_nice() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nice(100)
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --nice=100\n"));
+    }
+
+    #[test]
+    fn generate_script_with_negative_nice() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nice(-10)
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --nice=-10\n"));
+    }
+
+    #[test]
+    fn generate_script_without_nice() {
+        assert!(!sleep_job().generate_slurm_script().contains("--nice"));
+    }
+
+    #[test]
+    fn generate_script_with_exclusive() {
+        let job = SlurmJobBuilder::new(String::from("./benchmark"))
+            .set_exclusive(true)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --exclusive\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_exclusive_disabled() {
+        let job = SlurmJobBuilder::new(String::from("./benchmark"))
+            .set_exclusive(false)
+            .build();
+        assert!(!job.generate_slurm_script().contains("--exclusive"));
+    }
+
+    #[test]
+    fn generate_script_without_exclusive() {
+        assert!(!sleep_job().generate_slurm_script().contains("--exclusive"));
+    }
+
+    #[test]
+    fn generate_script_with_hybrid_layout() {
+        let job = SlurmJobBuilder::new(String::from("mpirun ./solver"))
+            .set_nodes(2)
+            .set_ntasks_per_node(4)
+            .set_cpus(8)
+            .build();
+        assert!(job.generate_slurm_script().contains(
+            "#SBATCH --nodes=2\n#SBATCH --ntasks-per-node=4\n#SBATCH --cpus-per-task=8\n"
+        ));
+    }
+
+    #[test]
+    fn generate_script_with_ntasks_per_node_only() {
+        let job = SlurmJobBuilder::new(String::from("srun hostname"))
+            .set_ntasks_per_node(16)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --ntasks-per-node=16\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_batch_signal() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_max_run_time("02:00:00".to_string())
+            .set_signal("B:USR1@120".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --time=02:00:00\n"));
+        assert!(script.contains("#SBATCH --signal=B:USR1@120\n"));
+    }
+
+    #[test]
+    fn generate_script_with_numeric_signal() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_signal("10@60".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --signal=10@60\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_signal() {
+        assert!(!sleep_job().generate_slurm_script().contains("--signal"));
+    }
+
+    #[test]
+    fn generate_script_with_comment_containing_spaces() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_comment("pipeline run 2024-06-01 #7".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --comment=\"pipeline run 2024-06-01 #7\"\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_comment_and_wckey() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_comment("run-42".to_string())
+            .set_wckey("genomics".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --comment=\"run-42\"\n"));
+        assert!(script.contains("#SBATCH --wckey=genomics\n"));
+    }
+
+    #[test]
+    fn generate_script_without_comment_or_wckey() {
+        let script = sleep_job().generate_slurm_script();
+        assert!(!script.contains("--comment") && !script.contains("--wckey"));
+    }
+
+    #[test]
+    fn generate_script_with_hold() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_held(true)
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --hold\n"));
+    }
+
+    #[test]
+    fn generate_script_without_hold() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_held(false)
+            .build();
+        assert!(!job.generate_slurm_script().contains("--hold"));
+    }
+
+    #[test]
+    fn generate_script_with_license() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_licenses("ansys:2".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --licenses=ansys:2\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_several_licenses() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_licenses("matlab:1,comsol:1".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --licenses=matlab:1,comsol:1\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_licenses() {
+        assert!(!sleep_job().generate_slurm_script().contains("--licenses"));
+    }
+
+    #[test]
+    fn generate_script_with_input_file() {
+        let job = SlurmJobBuilder::new(String::from("wc -l"))
+            .set_input_file("/data/samples.txt".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --input=/data/samples.txt\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_input_next_to_output_and_error() {
+        let job = SlurmJobBuilder::new(String::from("wc -l"))
+            .set_output_file("out.log".to_string())
+            .set_error_file("err.log".to_string())
+            .set_input_file("in.txt".to_string())
+            .build();
+        assert!(job.generate_slurm_script().contains(
+            "#SBATCH --output=out.log\n#SBATCH --error=err.log\n#SBATCH --input=in.txt\n"
+        ));
+    }
+
+    #[test]
+    fn generate_script_without_input_file() {
+        assert!(!sleep_job().generate_slurm_script().contains("--input"));
+    }
+
+    #[test]
+    fn generate_script_with_tmp_disk() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_memory(Memory::GigaByte(4))
+            .set_tmp_disk(Memory::GigaByte(100))
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --mem=4G\n#SBATCH --tmp=100G\n"));
+    }
+
+    #[test]
+    fn generate_script_with_terabyte_tmp_disk() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_tmp_disk(Memory::TeraByte(2))
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --tmp=2T\n"));
+    }
+
+    #[test]
+    fn generate_script_without_tmp_disk() {
+        assert!(!sleep_job().generate_slurm_script().contains("--tmp"));
+    }
+
+    #[test]
+    fn generate_script_with_cpu_freq_governor() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpu_freq("Performance".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --cpu-freq=Performance\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_cpu_freq_in_khz() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpu_freq("2400000".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --cpu-freq=2400000\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_cpu_freq() {
+        assert!(!sleep_job().generate_slurm_script().contains("--cpu-freq"));
+    }
+
+    #[test]
+    fn generate_script_without_hyperthreading() {
+        let job = SlurmJobBuilder::new(String::from("./stream"))
+            .set_cpus(8)
+            .set_threads_per_core(1)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --cpus-per-task=8\n#SBATCH --threads-per-core=1\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_threads_per_core() {
+        assert!(
+            !sleep_job()
+                .generate_slurm_script()
+                .contains("--threads-per-core")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_nodes_and_ntasks() {
+        let job = SlurmJobBuilder::new(String::from("mpirun ./solver"))
+            .set_nodes(4)
+            .set_ntasks(64)
+            .set_cpus(2)
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(
+            script.contains("#SBATCH --nodes=4\n#SBATCH --ntasks=64\n#SBATCH --cpus-per-task=2\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_ntasks_only() {
+        let job = SlurmJobBuilder::new(String::from("srun hostname"))
+            .set_ntasks(8)
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --ntasks=8\n"));
+        assert!(!script.contains("--nodes"));
+    }
+
+    #[test]
+    fn generate_script_without_nodes_and_ntasks() {
+        let script = sleep_job().generate_slurm_script();
+        assert!(!script.contains("--nodes"));
+        assert!(!script.contains("--ntasks"));
+    }
+
+    #[test]
+    fn generate_script_without_raw_directives() {
+        let script = sleep_job().generate_slurm_script();
+        let directives = script.lines().filter(|l| l.starts_with("#SBATCH")).count();
+        assert_eq!(directives, 4);
+    }
+
+    #[test]
+    fn generate_script_with_custom_id() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_id("run42".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --job-name=run42\n")
+        );
+        let named = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_id("run42".to_string())
+            .set_name("align".to_string())
+            .build();
+        assert_eq!(named.job_name(), "align-run42");
+    }
+
+    #[test]
+    fn generate_script_with_name() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_name("align-sample42".to_string())
+            .build();
+        assert_eq!(job.get_id().len(), 36);
+        assert!(job.generate_slurm_script().contains(
+            format!("#SBATCH --job-name=align-sample42-{}\n", &job.get_id()[..8]).as_str()
+        ));
+    }
+
+    #[test]
+    fn generate_script_with_same_name_stays_unique() {
+        let builder = SlurmJobBuilder::new(String::from("sleep 5")).set_name("align".to_string());
+        let (first, second) = (builder.build(), builder.build());
+        assert!(first.job_name().starts_with("align-"));
+        assert_ne!(first.job_name(), second.job_name());
+    }
+
+    #[test]
+    fn generate_script_without_name_uses_id() {
+        let job = sleep_job();
+        assert_eq!(&job.job_name(), job.get_id());
+    }
+
+    #[test]
+    fn generate_script_with_python_interpreter() {
+        let job = SlurmJobBuilder::new(String::from("import time\ntime.sleep(5)"))
+            .set_shell("/usr/bin/env python3".to_string())
+            .set_working_directory("/tmp/".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.starts_with("#!/usr/bin/env python3\n#SBATCH --job-name="));
+        assert!(script.contains("#SBATCH --chdir=/tmp/\n"));
+        assert!(script.ends_with("\n\nimport time\ntime.sleep(5)\n"));
+        assert!(!script.contains("echo START"));
+        assert!(!script.contains("pushd"));
+    }
+
+    #[test]
+    fn posix_shell_is_found_behind_flags_and_env() {
+        assert!(SlurmJob::is_posix_shell("/bin/bash -l"));
+        assert!(SlurmJob::is_posix_shell("/usr/bin/env -S bash -e"));
+        assert!(SlurmJob::is_posix_shell("/usr/bin/env LC_ALL=C sh"));
+        assert!(SlurmJob::is_posix_shell("/bin/zsh"));
+    }
+
+    #[test]
+    fn posix_shell_rejects_other_interpreters() {
+        assert!(!SlurmJob::is_posix_shell("/usr/bin/env python3"));
+        assert!(!SlurmJob::is_posix_shell("/usr/bin/perl -w"));
+        assert!(!SlurmJob::is_posix_shell("/usr/bin/python3 -u /bin/bash"));
+        assert!(!SlurmJob::is_posix_shell(""));
+    }
+
+    #[test]
+    fn generate_script_with_login_shell_keeps_wrapper() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_shell("/bin/bash -l".to_string())
+            .add_env("A".to_string(), "1".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.starts_with("#!/bin/bash -l\n"));
+        assert!(script.contains("export A='1'\n"));
+        assert!(script.contains("echo START"));
+    }
+
+    #[test]
+    fn generate_script_with_zsh_keeps_wrapper() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_shell("/bin/zsh".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.starts_with("#!/bin/zsh\n"));
+        assert!(script.contains("echo START"));
+        assert!(!script.contains("--chdir"));
+    }
+
+    #[test]
+    fn generate_script_defaults_to_bash() {
+        assert!(
+            sleep_job()
+                .generate_slurm_script()
+                .starts_with("#!/bin/bash\n")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "nodes must be at least 1")]
+    fn set_nodes_rejects_zero() {
+        SlurmJobBuilder::new(String::from("sleep 5")).set_nodes(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ntasks must be at least 1")]
+    fn set_ntasks_rejects_zero() {
+        SlurmJobBuilder::new(String::from("sleep 5")).set_ntasks(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ntasks per node must be at least 1")]
+    fn set_ntasks_per_node_rejects_zero() {
+        SlurmJobBuilder::new(String::from("sleep 5")).set_ntasks_per_node(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "threads per core must be at least 1")]
+    fn set_threads_per_core_rejects_zero() {
+        SlurmJobBuilder::new(String::from("sleep 5")).set_threads_per_core(0);
     }
 }
@@ -0,0 +1,101 @@
+use crate::job_builder::SlurmJobBuilder;
+use crate::memory_size::Memory;
+use serde::{Deserialize, Deserializer};
+use std::path::Path;
+
+// Site-wide settings applied to every job built with
+// SlurmJobBuilder::from_defaults, e.g. loaded from a TOML file like
+//
+//     account = "proj42"
+//     partition = "main"
+//     qos = "normal"
+//     memory = "4G"
+//     time = "02:00:00"
+//
+// Every key is optional, builder calls override the defaults.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlurmDefaults {
+    pub account: Option<String>,
+    pub partition: Option<String>,
+    pub qos: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_memory")]
+    pub memory: Option<Memory>,
+    pub time: Option<String>,
+}
+
+// memory is written as in job scripts, e.g. "4G" or "500M"
+fn deserialize_memory<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Memory>, D::Error> {
+    match Option::<String>::deserialize(deserializer)? {
+        Some(memory) => memory.parse().map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+impl SlurmDefaults {
+    pub fn from_toml(toml: &str) -> Result<SlurmDefaults, std::io::Error> {
+        let defaults: SlurmDefaults = toml::from_str(toml)
+            .map_err(|why| std::io::Error::new(std::io::ErrorKind::InvalidData, why))?;
+        if let Some(ref time) = defaults.time
+            && !SlurmJobBuilder::check_max_runtime_pattern(time)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid time format: {}", time),
+            ));
+        }
+        Ok(defaults)
+    }
+
+    pub fn load(path: &Path) -> Result<SlurmDefaults, std::io::Error> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml_reads_all_keys() {
+        let defaults = SlurmDefaults::from_toml(
+            "account = \"proj42\"\npartition = \"main\"\nqos = \"normal\"\nmemory = \"4G\"\ntime = \"02:00:00\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            defaults,
+            SlurmDefaults {
+                account: Some("proj42".to_string()),
+                partition: Some("main".to_string()),
+                qos: Some("normal".to_string()),
+                memory: Some(Memory::GigaByte(4)),
+                time: Some("02:00:00".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn from_toml_leaves_missing_keys_unset() {
+        let defaults = SlurmDefaults::from_toml("partition = \"gpu\"\n").unwrap();
+        assert_eq!(defaults.partition.as_deref(), Some("gpu"));
+        assert_eq!(defaults.memory, None);
+        assert_eq!(
+            SlurmDefaults::from_toml("").unwrap(),
+            SlurmDefaults::default()
+        );
+    }
+
+    #[test]
+    fn from_toml_rejects_invalid_values() {
+        assert!(SlurmDefaults::from_toml("memory = \"4X\"\n").is_err());
+        assert!(SlurmDefaults::from_toml("time = \"two hours\"\n").is_err());
+        assert!(SlurmDefaults::from_toml("acount = \"typo\"\n").is_err());
+    }
+
+    #[test]
+    fn load_reports_missing_file() {
+        assert!(SlurmDefaults::load(Path::new("/nonexistent/slurm.toml")).is_err());
+    }
+}
@@ -0,0 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+// Opaque reference to a job added to a SlurmManager. It stays valid while the
+// job moves between the open, scheduled and finished jobs of the manager.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JobHandle(pub(crate) usize);
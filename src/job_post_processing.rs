@@ -1,28 +1,80 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 
+// What is known about a job once it left the queue.
+pub struct PostProcessingContext {
+    pub(crate) job_id: String,
+    pub(crate) number: Option<i32>,
+    pub(crate) output_file: Option<PathBuf>,
+    pub(crate) error_file: Option<PathBuf>,
+    pub(crate) exit_code: Option<String>,
+}
+
+impl PostProcessingContext {
+    pub fn job_id(&self) -> &str {
+        &self.job_id
+    }
+
+    pub fn number(&self) -> Option<i32> {
+        self.number
+    }
+
+    // absolute path of the job's stdout with SLURM's %j, %x and %A patterns
+    // replaced. None if the path has symbols only SLURM can fill in, e.g. %a
+    // or %j of an array job, whose tasks each write their own file. The path
+    // is on the cluster, with an SshBackend it has to be fetched from there
+    // before it can be read.
+    pub fn output_file(&self) -> Option<&PathBuf> {
+        self.output_file.as_ref()
+    }
+
+    pub fn error_file(&self) -> Option<&PathBuf> {
+        self.error_file.as_ref()
+    }
+
+    // exit code reported by sacct in the form <code>:<signal>, None if sacct
+    // had no record of the job
+    pub fn exit_code(&self) -> Option<&str> {
+        self.exit_code.as_deref()
+    }
+}
+
+type Check = Arc<dyn Fn(&HashMap<String, String>, &PostProcessingContext) -> bool + Send + Sync>;
+
+// Checks may capture state (counters, connections, ...), clones share it.
 #[derive(Clone)]
 pub struct SlurmJobPostProcessing {
     param: HashMap<String, String>,
-    check: fn(&HashMap<String, String>) -> bool,
+    check: Check,
 }
 
 impl SlurmJobPostProcessing {
     pub fn new(
         param: &[(String, String)],
-        check: fn(&HashMap<String, String>) -> bool,
+        check: impl Fn(&HashMap<String, String>) -> bool + Send + Sync + 'static,
+    ) -> SlurmJobPostProcessing {
+        Self::with_context(param, move |param, _| check(param))
+    }
+
+    // the check additionally receives the job's id, number, log files and
+    // exit code
+    pub fn with_context(
+        param: &[(String, String)],
+        check: impl Fn(&HashMap<String, String>, &PostProcessingContext) -> bool + Send + Sync + 'static,
     ) -> SlurmJobPostProcessing {
         let param = HashMap::<String, String>::from_iter(param.iter().cloned());
-        SlurmJobPostProcessing { param, check }
+        SlurmJobPostProcessing {
+            param,
+            check: Arc::new(check),
+        }
     }
 
-    pub(crate) fn check(&self) -> bool {
-        (self.check)(&self.param)
+    pub(crate) fn check(&self, context: &PostProcessingContext) -> bool {
+        (self.check)(&self.param, context)
     }
 
     pub fn do_nothing() -> SlurmJobPostProcessing {
-        SlurmJobPostProcessing {
-            param: HashMap::new(),
-            check: |_| true,
-        }
+        Self::new(&[], |_| true)
     }
 }
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+// The state SLURM accounting (sacct) reports for a job, as opposed to the
+// manager-side SlurmJobStatus.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SlurmJobState {
+    Completed,
+    Failed,
+    Timeout,
+    OutOfMemory,
+    Cancelled,
+    NodeFail,
+    Preempted,
+    BootFail,
+    Deadline,
+    Pending,
+    Running,
+    Requeued,
+    Suspended,
+    Other(String),
+}
+
+impl SlurmJobState {
+    // sacct reports e.g. "CANCELLED by 1000", only the first word is the state
+    pub(crate) fn parse(state: &str) -> SlurmJobState {
+        match state.split_whitespace().next().unwrap_or("") {
+            "COMPLETED" => SlurmJobState::Completed,
+            "FAILED" => SlurmJobState::Failed,
+            "TIMEOUT" => SlurmJobState::Timeout,
+            "OUT_OF_MEMORY" => SlurmJobState::OutOfMemory,
+            "CANCELLED" => SlurmJobState::Cancelled,
+            "NODE_FAIL" => SlurmJobState::NodeFail,
+            "PREEMPTED" => SlurmJobState::Preempted,
+            "BOOT_FAIL" => SlurmJobState::BootFail,
+            "DEADLINE" => SlurmJobState::Deadline,
+            "PENDING" => SlurmJobState::Pending,
+            "RUNNING" => SlurmJobState::Running,
+            "REQUEUED" | "REQUEUE_HOLD" | "REQUEUE_FED" => SlurmJobState::Requeued,
+            "SUSPENDED" => SlurmJobState::Suspended,
+            other => SlurmJobState::Other(other.to_string()),
+        }
+    }
+
+    // a terminal state means SLURM will not run the job any further
+    pub fn is_terminal(&self) -> bool {
+        !matches!(
+            self,
+            SlurmJobState::Pending
+                | SlurmJobState::Running
+                | SlurmJobState::Requeued
+                | SlurmJobState::Suspended
+        )
+    }
+}
+
+impl Display for SlurmJobState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SlurmJobState::Completed => "COMPLETED",
+            SlurmJobState::Failed => "FAILED",
+            SlurmJobState::Timeout => "TIMEOUT",
+            SlurmJobState::OutOfMemory => "OUT_OF_MEMORY",
+            SlurmJobState::Cancelled => "CANCELLED",
+            SlurmJobState::NodeFail => "NODE_FAIL",
+            SlurmJobState::Preempted => "PREEMPTED",
+            SlurmJobState::BootFail => "BOOT_FAIL",
+            SlurmJobState::Deadline => "DEADLINE",
+            SlurmJobState::Pending => "PENDING",
+            SlurmJobState::Running => "RUNNING",
+            SlurmJobState::Requeued => "REQUEUED",
+            SlurmJobState::Suspended => "SUSPENDED",
+            SlurmJobState::Other(name) => name.as_str(),
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_known_states() {
+        assert_eq!(SlurmJobState::parse("COMPLETED"), SlurmJobState::Completed);
+        assert_eq!(
+            SlurmJobState::parse("OUT_OF_MEMORY"),
+            SlurmJobState::OutOfMemory
+        );
+    }
+
+    #[test]
+    fn parse_cancelled_with_user_suffix() {
+        assert_eq!(
+            SlurmJobState::parse("CANCELLED by 1000"),
+            SlurmJobState::Cancelled
+        );
+    }
+
+    #[test]
+    fn parse_unknown_state_is_kept_verbatim() {
+        let state = SlurmJobState::parse("SPECIAL_EXIT");
+        assert_eq!(state, SlurmJobState::Other("SPECIAL_EXIT".to_string()));
+        assert_eq!(state.to_string(), "SPECIAL_EXIT");
+    }
+
+    #[test]
+    fn terminal_states() {
+        assert!(SlurmJobState::Completed.is_terminal());
+        assert!(SlurmJobState::Timeout.is_terminal());
+        assert!(!SlurmJobState::Requeued.is_terminal());
+        assert!(!SlurmJobState::Running.is_terminal());
+    }
+}
@@ -1,8 +1,99 @@
-#[derive(Clone, PartialEq, Eq)]
-pub(crate) enum SlurmJobStatus {
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum SlurmJobStatus {
     CREATED,
     PENDING,
     SUBMITTED,
+    QUEUED,
+    HELD,
+    RUNNING,
     FINISHED,
     CRASHED,
+    CANCELLED,
+    FAILED,
+    SKIPPED,
+    TIMEOUT,
+}
+
+impl SlurmJobStatus {
+    // the manager is done with jobs in these states
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            SlurmJobStatus::FINISHED
+                | SlurmJobStatus::CRASHED
+                | SlurmJobStatus::CANCELLED
+                | SlurmJobStatus::FAILED
+                | SlurmJobStatus::SKIPPED
+                | SlurmJobStatus::TIMEOUT
+        )
+    }
+
+    // final states of jobs that did not get their work done, SKIPPED and
+    // CANCELLED jobs were stopped on purpose
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            SlurmJobStatus::CRASHED | SlurmJobStatus::FAILED | SlurmJobStatus::TIMEOUT
+        )
+    }
+}
+
+impl Display for SlurmJobStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SlurmJobStatus::CREATED => "CREATED",
+            SlurmJobStatus::PENDING => "PENDING",
+            SlurmJobStatus::SUBMITTED => "SUBMITTED",
+            SlurmJobStatus::QUEUED => "QUEUED",
+            SlurmJobStatus::HELD => "HELD",
+            SlurmJobStatus::RUNNING => "RUNNING",
+            SlurmJobStatus::FINISHED => "FINISHED",
+            SlurmJobStatus::CRASHED => "CRASHED",
+            SlurmJobStatus::CANCELLED => "CANCELLED",
+            SlurmJobStatus::FAILED => "FAILED",
+            SlurmJobStatus::SKIPPED => "SKIPPED",
+            SlurmJobStatus::TIMEOUT => "TIMEOUT",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_uses_variant_name() {
+        assert_eq!(SlurmJobStatus::SUBMITTED.to_string(), "SUBMITTED");
+    }
+
+    #[test]
+    fn outcomes_are_final() {
+        assert!(SlurmJobStatus::FINISHED.is_final());
+        assert!(SlurmJobStatus::SKIPPED.is_final());
+    }
+
+    #[test]
+    fn live_states_are_not_final() {
+        assert!(!SlurmJobStatus::QUEUED.is_final());
+        assert!(!SlurmJobStatus::RUNNING.is_final());
+    }
+
+    #[test]
+    fn crashes_are_failures() {
+        assert!(SlurmJobStatus::CRASHED.is_failure());
+        assert!(SlurmJobStatus::TIMEOUT.is_failure());
+        assert!(!SlurmJobStatus::CANCELLED.is_failure());
+    }
+
+    #[test]
+    fn display_distinguishes_outcomes() {
+        assert_eq!(SlurmJobStatus::FINISHED.to_string(), "FINISHED");
+        assert_eq!(SlurmJobStatus::CRASHED.to_string(), "CRASHED");
+        assert_eq!(SlurmJobStatus::TIMEOUT.to_string(), "TIMEOUT");
+    }
 }
@@ -0,0 +1,114 @@
+use crate::memory_size::Memory;
+use std::time::Duration;
+
+// Resources a finished job used according to
+// `sacct --format=Elapsed,MaxRSS,TotalCPU`. The values are None while sacct
+// has no record of the job.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobUsage {
+    pub job_id: String,
+    pub number: i32,
+    pub elapsed: Option<Duration>,
+    pub total_cpu: Option<Duration>,
+    pub max_rss: Option<Memory>,
+}
+
+impl JobUsage {
+    // CPU time actually used, summed over all CPUs, in hours
+    pub fn cpu_hours(&self) -> Option<f64> {
+        self.total_cpu
+            .map(|total_cpu| total_cpu.as_secs_f64() / 3600.0)
+    }
+
+    // the first row of `sacct -n -P` describes the whole job, MaxRSS is only
+    // reported for its steps so the peak is taken over all rows
+    pub(crate) fn parse_sacct(job_id: &str, number: i32, out: &str) -> JobUsage {
+        let rows: Vec<Vec<&str>> = out
+            .lines()
+            .filter(|row| !row.trim().is_empty())
+            .map(|row| row.trim().split('|').collect())
+            .filter(|fields: &Vec<&str>| fields.len() == 3)
+            .collect();
+        let max_rss = rows
+            .iter()
+            .filter_map(|fields| Self::parse_rss(fields[1]))
+            .max_by_key(|rss| rss.as_bytes());
+        JobUsage {
+            job_id: job_id.to_string(),
+            number,
+            elapsed: rows
+                .first()
+                .and_then(|fields| Self::parse_duration(fields[0])),
+            total_cpu: rows
+                .first()
+                .and_then(|fields| Self::parse_duration(fields[2])),
+            max_rss,
+        }
+    }
+
+    // sacct prints "0" without a unit for steps that used no memory
+    fn parse_rss(rss: &str) -> Option<Memory> {
+        match rss.trim() {
+            "" => None,
+            "0" => Some(Memory::KiloByte(0)),
+            rss => rss.parse().ok(),
+        }
+    }
+
+    // [DD-][HH:]MM:SS[.mmm], e.g. "1-02:03:04" or "00:01.234"
+    fn parse_duration(duration: &str) -> Option<Duration> {
+        let (days, time) = match duration.trim().split_once('-') {
+            Some((days, time)) => (days.parse::<u64>().ok()?, time),
+            None => (0, duration.trim()),
+        };
+        let parts: Vec<&str> = time.split(':').collect();
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes, seconds] => (hours.parse::<u64>().ok()?, minutes, seconds),
+            [minutes, seconds] => (0, minutes, seconds),
+            _ => return None,
+        };
+        let minutes = minutes.parse::<u64>().ok()?;
+        let seconds = seconds.parse::<f64>().ok()?;
+        if !seconds.is_finite() || seconds < 0.0 {
+            return None;
+        }
+        let whole = ((days * 24 + hours) * 60 + minutes) * 60;
+        Some(Duration::from_secs(whole) + Duration::from_secs_f64(seconds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sacct_takes_peak_rss_of_all_steps() {
+        let out = "00:10:00||00:35:12\n00:10:00|2136K|00:35:11\n00:10:00|1G|00:00:01\n";
+        let usage = JobUsage::parse_sacct("abc", 42, out);
+        assert_eq!(usage.number, 42);
+        assert_eq!(usage.elapsed, Some(Duration::from_secs(600)));
+        assert_eq!(usage.total_cpu, Some(Duration::from_secs(35 * 60 + 12)));
+        assert_eq!(usage.max_rss, Some(Memory::GigaByte(1)));
+        assert_eq!(usage.cpu_hours(), Some(2112.0 / 3600.0));
+    }
+
+    #[test]
+    fn parse_duration_accepts_days_and_fractions() {
+        assert_eq!(
+            JobUsage::parse_duration("1-02:03:04"),
+            Some(Duration::from_secs(((24 + 2) * 60 + 3) * 60 + 4))
+        );
+        assert_eq!(
+            JobUsage::parse_duration("01:02.500"),
+            Some(Duration::from_millis(62_500))
+        );
+    }
+
+    #[test]
+    fn parse_sacct_without_record() {
+        let usage = JobUsage::parse_sacct("abc", 42, "");
+        assert_eq!(usage.elapsed, None);
+        assert_eq!(usage.max_rss, None);
+        assert_eq!(usage.cpu_hours(), None);
+    }
+}
@@ -1,9 +1,24 @@
+pub mod build_error;
+pub mod het_component;
 pub mod job;
+pub mod job_bucket;
 pub mod job_builder;
+pub mod job_defaults;
+pub mod job_handle;
 pub mod job_post_processing;
-mod job_status;
+pub mod job_state;
+pub mod job_status;
+pub mod job_usage;
+pub mod local_backend;
+pub mod mail_type;
+pub mod manager_summary;
 pub mod memory_size;
+#[cfg(unix)]
+pub mod mock_scheduler;
+pub mod slurm_backend;
 pub mod slurm_manager;
+pub mod ssh_backend;
+pub mod tick_result;
 
 #[cfg(test)]
 mod tests {
@@ -36,6 +51,6 @@ mod tests {
         for _ in 0..5 {
             manager.add_job(&generate_job());
         }
-        manager.manage_jobs(None);
+        assert_eq!(manager.manage_jobs(None), Ok(true));
     }
 }
@@ -0,0 +1,119 @@
+use crate::slurm_backend::{
+    DEFAULT_COMMAND_TIMEOUT, FORMAT_OVERRIDES, SlurmBackend, output_with_input,
+};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+// Runs the SLURM binaries on this machine, the default backend.
+#[derive(Clone, Debug)]
+pub struct LocalBackend {
+    binary_path: Option<PathBuf>,
+    command_timeout: Duration,
+}
+
+impl Default for LocalBackend {
+    fn default() -> LocalBackend {
+        LocalBackend::new()
+    }
+}
+
+impl LocalBackend {
+    pub fn new() -> LocalBackend {
+        LocalBackend {
+            binary_path: None,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    // directory containing sbatch, squeue, sacct and scancel, by default they
+    // are looked up on PATH
+    pub fn with_binary_path(binary_path: PathBuf) -> LocalBackend {
+        LocalBackend {
+            binary_path: Some(binary_path),
+            ..LocalBackend::new()
+        }
+    }
+
+    fn command(&self, program: &str) -> Command {
+        let mut command = match self.binary_path {
+            Some(ref binary_path) => Command::new(binary_path.join(program)),
+            None => Command::new(program),
+        };
+        for variable in FORMAT_OVERRIDES {
+            command.env_remove(variable);
+        }
+        command
+    }
+}
+
+impl SlurmBackend for LocalBackend {
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> std::io::Result<Output> {
+        let mut command = self.command(program);
+        command.args(args);
+        output_with_input(command, input, self.command_timeout)
+    }
+
+    fn set_command_timeout(&mut self, timeout: Duration) {
+        self.command_timeout = timeout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_defaults_to_path_lookup() {
+        assert_eq!(
+            LocalBackend::new().command("sbatch").get_program(),
+            "sbatch"
+        );
+    }
+
+    #[test]
+    fn command_uses_binary_path() {
+        let backend = LocalBackend::with_binary_path(PathBuf::from("/opt/slurm/bin"));
+        assert_eq!(
+            backend.command("squeue").get_program(),
+            "/opt/slurm/bin/squeue"
+        );
+        assert_eq!(
+            backend.command("scancel").get_program(),
+            "/opt/slurm/bin/scancel"
+        );
+    }
+
+    #[test]
+    fn command_ignores_format_overrides() {
+        let command = LocalBackend::new().command("squeue");
+        let removed: Vec<_> = command
+            .get_envs()
+            .filter(|(_, value)| value.is_none())
+            .map(|(variable, _)| variable.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(removed, ["SACCT_FORMAT", "SQUEUE_FORMAT", "SQUEUE_FORMAT2"]);
+    }
+
+    #[test]
+    fn run_passes_arguments() {
+        let output = LocalBackend::new()
+            .run("echo", &["-n".to_string(), "%.i %.t".to_string()], None)
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "%.i %.t");
+    }
+
+    #[test]
+    fn run_reports_command_exceeding_timeout() {
+        let mut backend = LocalBackend::new();
+        backend.set_command_timeout(Duration::from_millis(50));
+        let why = backend.run("sleep", &["5".to_string()], None).unwrap_err();
+        assert_eq!(why.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn run_reports_missing_binary() {
+        let backend = LocalBackend::with_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        assert!(backend.run("squeue", &[], None).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+// Events SLURM sends an email for, see `--mail-type` in sbatch(1).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::upper_case_acronyms, non_camel_case_types)]
+pub enum MailType {
+    NONE,
+    BEGIN,
+    END,
+    FAIL,
+    REQUEUE,
+    ALL,
+    TIME_LIMIT,
+    ARRAY_TASKS,
+}
+
+impl Display for MailType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MailType::NONE => "NONE",
+            MailType::BEGIN => "BEGIN",
+            MailType::END => "END",
+            MailType::FAIL => "FAIL",
+            MailType::REQUEUE => "REQUEUE",
+            MailType::ALL => "ALL",
+            MailType::TIME_LIMIT => "TIME_LIMIT",
+            MailType::ARRAY_TASKS => "ARRAY_TASKS",
+        };
+        f.write_str(name)
+    }
+}
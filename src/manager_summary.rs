@@ -0,0 +1,71 @@
+use std::fmt::{Display, Formatter};
+
+// Snapshot of how many jobs a SlurmManager holds in each stage. Crashed jobs
+// (crashed, failed to submit or timed out) are also counted as finished, held
+// jobs are also counted as scheduled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub open: usize,
+    pub scheduled: usize,
+    pub held: usize,
+    pub finished: usize,
+    pub crashed: usize,
+    pub total: usize,
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} finished ({} crashed), {} scheduled",
+            self.finished, self.total, self.crashed, self.scheduled
+        )?;
+        if self.held > 0 {
+            write!(f, " ({} held)", self.held)?;
+        }
+        write!(f, ", {} open", self.open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_lists_all_counts() {
+        let summary = Summary {
+            open: 3,
+            scheduled: 2,
+            held: 0,
+            finished: 5,
+            crashed: 1,
+            total: 10,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "5/10 finished (1 crashed), 2 scheduled, 3 open"
+        );
+    }
+
+    #[test]
+    fn display_mentions_held_jobs() {
+        let summary = Summary {
+            scheduled: 3,
+            held: 2,
+            total: 3,
+            ..Summary::default()
+        };
+        assert_eq!(
+            summary.to_string(),
+            "0/3 finished (0 crashed), 3 scheduled (2 held), 0 open"
+        );
+    }
+
+    #[test]
+    fn default_is_empty() {
+        assert_eq!(
+            Summary::default().to_string(),
+            "0/0 finished (0 crashed), 0 scheduled, 0 open"
+        );
+    }
+}
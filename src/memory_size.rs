@@ -1,6 +1,186 @@
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Memory {
+    KiloByte(u64),
     MegaByte(u32),
     #[allow(unused)]
     GigaByte(u32),
+    TeraByte(u64),
+}
+
+// SLURM uses binary units, i.e. 1G is 1024M.
+impl Memory {
+    // kilobyte amounts are rounded up to the next full megabyte
+    pub fn as_megabytes(&self) -> u64 {
+        match self {
+            Memory::KiloByte(amount) => amount.div_ceil(1024),
+            Memory::MegaByte(amount) => u64::from(*amount),
+            Memory::GigaByte(amount) => u64::from(*amount) * 1024,
+            Memory::TeraByte(amount) => amount.saturating_mul(1024 * 1024),
+        }
+    }
+
+    pub fn as_bytes(&self) -> u64 {
+        match self {
+            Memory::KiloByte(amount) => amount.saturating_mul(1024),
+            _ => self.as_megabytes().saturating_mul(1024 * 1024),
+        }
+    }
+}
+
+// Formats the size the way sbatch expects it, e.g. "100M" or "4G".
+impl Display for Memory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Memory::KiloByte(amount) => write!(f, "{}K", amount),
+            Memory::MegaByte(amount) => write!(f, "{}M", amount),
+            Memory::GigaByte(amount) => write!(f, "{}G", amount),
+            Memory::TeraByte(amount) => write!(f, "{}T", amount),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseMemoryError {
+    Empty,
+    MissingUnit(String),
+    UnknownUnit(String),
+    InvalidNumber(String),
+}
+
+impl Display for ParseMemoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseMemoryError::Empty => f.write_str("memory size must not be empty"),
+            ParseMemoryError::MissingUnit(value) => {
+                write!(f, "memory size {} is missing a unit (K, M, G, T)", value)
+            }
+            ParseMemoryError::UnknownUnit(unit) => {
+                write!(f, "unknown memory unit {}, expected K, M, G or T", unit)
+            }
+            ParseMemoryError::InvalidNumber(number) => {
+                write!(f, "invalid memory amount {}", number)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseMemoryError {}
+
+// Parses sizes such as "512M", "4G" or "16gb", the unit is case-insensitive
+// and may be K/KB, M/MB, G/GB or T/TB.
+impl FromStr for Memory {
+    type Err = ParseMemoryError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(ParseMemoryError::Empty);
+        }
+        let split = value
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| ParseMemoryError::MissingUnit(value.to_string()))?;
+        let (number, unit) = value.split_at(split);
+        let invalid_number = || ParseMemoryError::InvalidNumber(number.to_string());
+        let amount: u64 = number.parse().map_err(|_| invalid_number())?;
+        match unit.to_ascii_uppercase().as_str() {
+            "K" | "KB" => Ok(Memory::KiloByte(amount)),
+            "M" | "MB" => Ok(Memory::MegaByte(
+                u32::try_from(amount).map_err(|_| invalid_number())?,
+            )),
+            "G" | "GB" => Ok(Memory::GigaByte(
+                u32::try_from(amount).map_err(|_| invalid_number())?,
+            )),
+            "T" | "TB" => Ok(Memory::TeraByte(amount)),
+            _ => Err(ParseMemoryError::UnknownUnit(unit.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gigabyte_as_megabytes() {
+        assert_eq!(Memory::GigaByte(1).as_megabytes(), 1024);
+        assert_eq!(Memory::TeraByte(1).as_megabytes(), 1024 * 1024);
+    }
+
+    #[test]
+    fn kilobytes_round_up_to_megabytes() {
+        assert_eq!(Memory::KiloByte(1024).as_megabytes(), 1);
+        assert_eq!(Memory::KiloByte(1025).as_megabytes(), 2);
+        assert_eq!(Memory::KiloByte(0).as_megabytes(), 0);
+    }
+
+    #[test]
+    fn as_bytes_is_exact() {
+        assert_eq!(Memory::KiloByte(3).as_bytes(), 3 * 1024);
+        assert_eq!(Memory::MegaByte(1).as_bytes(), 1024 * 1024);
+        assert_eq!(Memory::TeraByte(u64::MAX).as_bytes(), u64::MAX);
+    }
+
+    #[test]
+    fn display_uses_slurm_units() {
+        assert_eq!(Memory::MegaByte(100).to_string(), "100M");
+        assert_eq!(Memory::TeraByte(2).to_string(), "2T");
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        let memory = Memory::GigaByte(8);
+        assert_eq!(memory.to_string().parse(), Ok(memory));
+    }
+
+    #[test]
+    fn parse_megabytes() {
+        assert_eq!("512M".parse(), Ok(Memory::MegaByte(512)));
+        assert_eq!("512mb".parse(), Ok(Memory::MegaByte(512)));
+    }
+
+    #[test]
+    fn parse_gigabytes() {
+        assert_eq!("4G".parse(), Ok(Memory::GigaByte(4)));
+        assert_eq!(" 16Gb ".parse(), Ok(Memory::GigaByte(16)));
+    }
+
+    #[test]
+    fn parse_kilobytes_and_terabytes() {
+        assert_eq!("64K".parse(), Ok(Memory::KiloByte(64)));
+        assert_eq!("2tb".parse(), Ok(Memory::TeraByte(2)));
+    }
+
+    #[test]
+    fn parse_rejects_empty_string() {
+        assert_eq!("".parse::<Memory>(), Err(ParseMemoryError::Empty));
+        assert_eq!("   ".parse::<Memory>(), Err(ParseMemoryError::Empty));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_unit() {
+        assert_eq!(
+            "4X".parse::<Memory>(),
+            Err(ParseMemoryError::UnknownUnit("X".to_string()))
+        );
+        assert_eq!(
+            "4".parse::<Memory>(),
+            Err(ParseMemoryError::MissingUnit("4".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_number() {
+        assert_eq!(
+            "G".parse::<Memory>(),
+            Err(ParseMemoryError::InvalidNumber("".to_string()))
+        );
+        assert_eq!(
+            "99999999999M".parse::<Memory>(),
+            Err(ParseMemoryError::InvalidNumber("99999999999".to_string()))
+        );
+    }
 }
@@ -0,0 +1,387 @@
+use crate::slurm_backend::SlurmBackend;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::os::unix::process::ExitStatusExt;
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+
+// In-memory stand-in for a SLURM cluster, to test code driving a SlurmManager
+// without a cluster. Clones share the same state, so a test keeps one clone
+// to control the jobs while the manager owns the other:
+// submitted jobs are queued until `start` or `finish` is called for them.
+// Scripts with `#SBATCH --hold` (or jobs held with `scontrol hold`) stay held
+// until `scontrol release`.
+#[derive(Clone, Default)]
+pub struct MockScheduler {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    last_number: i32,
+    queue: BTreeMap<i32, &'static str>,
+    accounting: HashMap<i32, String>,
+    usage: HashMap<i32, String>,
+    scripts: Vec<String>,
+    cancelled: Vec<i32>,
+    held: BTreeSet<i32>,
+    priorities: HashMap<i32, u32>,
+    rejections: u32,
+    squeue_failures: u32,
+}
+
+fn output(code: i32, stdout: String, stderr: &str) -> Output {
+    Output {
+        status: ExitStatus::from_raw(code << 8),
+        stdout: stdout.into_bytes(),
+        stderr: stderr.as_bytes().to_vec(),
+    }
+}
+
+impl MockScheduler {
+    pub fn new() -> MockScheduler {
+        MockScheduler::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock scheduler state poisoned")
+    }
+
+    // every script passed to sbatch, in submission order. Job numbers start
+    // at 1, so the script of job n is at index n - 1.
+    pub fn submitted_scripts(&self) -> Vec<String> {
+        self.state().scripts.clone()
+    }
+
+    // numbers of the jobs still listed by squeue
+    pub fn queued_jobs(&self) -> Vec<i32> {
+        self.state().queue.keys().copied().collect()
+    }
+
+    pub fn cancelled_jobs(&self) -> Vec<i32> {
+        self.state().cancelled.clone()
+    }
+
+    // the next `count` submissions fail as if sbatch rejected them
+    pub fn reject_submissions(&self, count: u32) {
+        self.state().rejections = count;
+    }
+
+    // the next `count` squeue calls fail, e.g. as for an unknown partition
+    pub fn fail_squeue(&self, count: u32) {
+        self.state().squeue_failures = count;
+    }
+
+    // numbers of the jobs submitted held and not released yet
+    pub fn held_jobs(&self) -> Vec<i32> {
+        self.state().held.iter().copied().collect()
+    }
+
+    // the priority set with `scontrol update JobId=<number> Priority=<p>`
+    pub fn priority(&self, number: i32) -> Option<u32> {
+        self.state().priorities.get(&number).copied()
+    }
+
+    // moves a queued job from PD to R
+    pub fn start(&self, number: i32) {
+        let mut state = self.state();
+        let job = state
+            .queue
+            .get_mut(&number)
+            .unwrap_or_else(|| panic!("job {} is not queued", number));
+        *job = "R";
+    }
+
+    // what sacct reports as Elapsed, MaxRSS and TotalCPU of the job
+    pub fn record_usage(&self, number: i32, elapsed: &str, max_rss: &str, total_cpu: &str) {
+        self.state()
+            .usage
+            .insert(number, format!("{}|{}|{}", elapsed, max_rss, total_cpu));
+    }
+
+    // removes the job from the queue, sacct then reports `state` (e.g.
+    // "COMPLETED" or "FAILED") and `exit_code` (e.g. "0:0") for it
+    pub fn finish(&self, number: i32, state: &str, exit_code: &str) {
+        let mut mock = self.state();
+        assert!(
+            mock.queue.remove(&number).is_some(),
+            "job {} is not queued",
+            number
+        );
+        mock.accounting
+            .insert(number, format!("{}|{}", state, exit_code));
+    }
+
+    fn sbatch(&self, input: Option<&str>) -> Output {
+        let mut state = self.state();
+        if state.rejections > 0 {
+            state.rejections -= 1;
+            return output(
+                1,
+                String::new(),
+                "sbatch: error: Batch job submission failed",
+            );
+        }
+        state.last_number += 1;
+        let number = state.last_number;
+        let script = input.unwrap_or_default();
+        if script.lines().any(|line| line == "#SBATCH --hold") {
+            state.held.insert(number);
+        }
+        state.scripts.push(script.to_string());
+        state.queue.insert(number, "PD");
+        output(0, format!("Submitted batch job {}\n", number), "")
+    }
+
+    fn squeue(&self, args: &[String]) -> Output {
+        let mut out = String::new();
+        if !args.iter().any(|arg| arg == "--noheader") {
+            out += "JOBID PARTITION NAME USER ST TIME NODES NODELIST(REASON)\n";
+        }
+        let mut state = self.state();
+        if state.squeue_failures > 0 {
+            state.squeue_failures -= 1;
+            return output(
+                1,
+                String::new(),
+                "squeue: error: Invalid partition name specified",
+            );
+        }
+        for (number, st) in state.queue.iter() {
+            let reason = match *st {
+                "PD" if state.held.contains(number) => "(JobHeldUser)",
+                "PD" => "(Priority)",
+                _ => "node01",
+            };
+            out += &format!("{} main job user {} 0:00 1 {}\n", number, st, reason);
+        }
+        output(0, out, "")
+    }
+
+    fn sacct(&self, args: &[String]) -> Output {
+        let number = args
+            .iter()
+            .skip_while(|arg| *arg != "-j")
+            .nth(1)
+            .and_then(|number| number.parse::<i32>().ok());
+        let state = self.state();
+        let rows = if args
+            .iter()
+            .any(|arg| arg == "--format=Elapsed,MaxRSS,TotalCPU")
+        {
+            &state.usage
+        } else {
+            &state.accounting
+        };
+        match number.and_then(|number| rows.get(&number).cloned()) {
+            Some(row) => output(0, row + "\n", ""),
+            None => output(0, String::new(), ""),
+        }
+    }
+
+    // only `scontrol release|hold|requeue <number>` and
+    // `scontrol update JobId=<number> Priority=<p>` are simulated
+    fn scontrol(&self, args: &[String]) -> Output {
+        let mut state = self.state();
+        match args {
+            [command, number] if command == "release" => match number.parse::<i32>() {
+                Ok(number) if state.queue.contains_key(&number) => {
+                    state.held.remove(&number);
+                    output(0, String::new(), "")
+                }
+                _ => output(
+                    1,
+                    String::new(),
+                    "scontrol: error: Invalid job id specified",
+                ),
+            },
+            [command, number] if command == "hold" => match number.parse::<i32>() {
+                Ok(number) if state.queue.get(&number) == Some(&"PD") => {
+                    state.held.insert(number);
+                    output(0, String::new(), "")
+                }
+                _ => output(
+                    1,
+                    String::new(),
+                    "scontrol: error: Job is no longer pending execution",
+                ),
+            },
+            [command, number] if command == "requeue" => match number.parse::<i32>() {
+                Ok(number) if state.queue.get(&number) == Some(&"R") => {
+                    state.queue.insert(number, "PD");
+                    output(0, String::new(), "")
+                }
+                _ => output(
+                    1,
+                    String::new(),
+                    "scontrol: error: Invalid job id specified",
+                ),
+            },
+            [command, job, priority] if command == "update" => {
+                let number = job.strip_prefix("JobId=").and_then(|n| n.parse().ok());
+                let priority = priority
+                    .strip_prefix("Priority=")
+                    .and_then(|p| p.parse().ok());
+                match (number, priority) {
+                    (Some(number), Some(priority)) if state.queue.contains_key(&number) => {
+                        state.priorities.insert(number, priority);
+                        output(0, String::new(), "")
+                    }
+                    _ => output(
+                        1,
+                        String::new(),
+                        "scontrol: error: Invalid job id specified",
+                    ),
+                }
+            }
+            _ => output(1, String::new(), "scontrol: error: not simulated"),
+        }
+    }
+
+    fn scancel(&self, args: &[String]) -> Output {
+        let number = args.first().and_then(|number| number.parse::<i32>().ok());
+        let mut state = self.state();
+        match number {
+            Some(number) if state.queue.remove(&number).is_some() => {
+                state.held.remove(&number);
+                state.cancelled.push(number);
+                state
+                    .accounting
+                    .insert(number, String::from("CANCELLED|0:15"));
+                output(0, String::new(), "")
+            }
+            _ => output(1, String::new(), "scancel: error: Invalid job id specified"),
+        }
+    }
+}
+
+impl SlurmBackend for MockScheduler {
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> std::io::Result<Output> {
+        match program {
+            "sbatch" => Ok(self.sbatch(input)),
+            "squeue" => Ok(self.squeue(args)),
+            "sacct" => Ok(self.sacct(args)),
+            "scancel" => Ok(self.scancel(args)),
+            "scontrol" => Ok(self.scontrol(args)),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not simulated", program),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(mock: &MockScheduler, program: &str, args: &[&str], input: Option<&str>) -> Output {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        mock.run(program, &args, input).expect("simulated program")
+    }
+
+    fn stdout(output: &Output) -> String {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    #[test]
+    fn submissions_are_numbered_and_queued() {
+        let mock = MockScheduler::new();
+        assert_eq!(
+            stdout(&run(&mock, "sbatch", &[], Some("#!/bin/bash\n"))),
+            "Submitted batch job 1\n"
+        );
+        assert_eq!(
+            stdout(&run(&mock, "sbatch", &[], Some("#!/bin/sh\n"))),
+            "Submitted batch job 2\n"
+        );
+        assert_eq!(mock.queued_jobs(), [1, 2]);
+        assert_eq!(mock.submitted_scripts(), ["#!/bin/bash\n", "#!/bin/sh\n"]);
+    }
+
+    #[test]
+    fn finished_jobs_leave_the_queue_and_show_up_in_sacct() {
+        let mock = MockScheduler::new();
+        run(&mock, "sbatch", &[], Some(""));
+        run(&mock, "sbatch", &[], Some(""));
+        mock.start(2);
+        let queue = stdout(&run(&mock, "squeue", &[], None));
+        assert!(queue.contains("\n1 main job user PD 0:00 1 (Priority)\n"));
+        assert!(queue.contains("\n2 main job user R 0:00 1 node01\n"));
+        mock.finish(1, "COMPLETED", "0:0");
+        assert_eq!(mock.queued_jobs(), [2]);
+        let args = ["-j".to_string(), "1".to_string()];
+        assert_eq!(
+            stdout(&mock.run("sacct", &args, None).unwrap()),
+            "COMPLETED|0:0\n"
+        );
+    }
+
+    #[test]
+    fn rejected_submissions_and_unknown_cancellations_fail() {
+        let mock = MockScheduler::new();
+        mock.reject_submissions(1);
+        assert!(!run(&mock, "sbatch", &[], Some("")).status.success());
+        assert!(run(&mock, "sbatch", &[], Some("")).status.success());
+        assert!(!run(&mock, "scancel", &["7"], None).status.success());
+        assert!(run(&mock, "scancel", &["1"], None).status.success());
+        assert_eq!(mock.cancelled_jobs(), [1]);
+        assert!(mock.run("sinfo", &[], None).is_err());
+    }
+
+    #[test]
+    fn failing_squeue_recovers_after_count() {
+        let mock = MockScheduler::new();
+        mock.fail_squeue(1);
+        assert!(!run(&mock, "squeue", &[], None).status.success());
+        assert!(run(&mock, "squeue", &[], None).status.success());
+    }
+
+    #[test]
+    fn held_submissions_are_pending_until_released() {
+        let mock = MockScheduler::new();
+        run(&mock, "sbatch", &[], Some("#!/bin/bash\n#SBATCH --hold\n"));
+        run(&mock, "sbatch", &[], Some("#!/bin/bash\n"));
+        assert_eq!(mock.held_jobs(), [1]);
+        let queue = stdout(&run(&mock, "squeue", &[], None));
+        assert!(queue.contains("\n1 main job user PD 0:00 1 (JobHeldUser)\n"));
+        assert!(queue.contains("\n2 main job user PD 0:00 1 (Priority)\n"));
+        assert!(
+            run(&mock, "scontrol", &["release", "1"], None)
+                .status
+                .success()
+        );
+        assert!(mock.held_jobs().is_empty());
+    }
+
+    #[test]
+    fn pending_jobs_can_be_held_and_running_ones_requeued() {
+        let mock = MockScheduler::new();
+        run(&mock, "sbatch", &[], Some("#!/bin/bash\n"));
+        run(&mock, "sbatch", &[], Some("#!/bin/bash\n"));
+        mock.start(2);
+        let scontrol = |command: &str, number: i32| {
+            let args = [command.to_string(), number.to_string()];
+            mock.run("scontrol", &args, None).unwrap().status.success()
+        };
+        assert!(scontrol("hold", 1));
+        assert!(!scontrol("hold", 2));
+        assert!(scontrol("requeue", 2));
+        assert_eq!(mock.held_jobs(), [1]);
+        assert!(
+            stdout(&run(&mock, "squeue", &[], None))
+                .contains("\n2 main job user PD 0:00 1 (Priority)\n")
+        );
+    }
+
+    #[test]
+    fn releasing_unknown_jobs_fails() {
+        let mock = MockScheduler::new();
+        assert!(
+            !run(&mock, "scontrol", &["release", "3"], None)
+                .status
+                .success()
+        );
+        let args = ["hold".to_string(), "3".to_string()];
+        assert!(!mock.run("scontrol", &args, None).unwrap().status.success());
+    }
+}
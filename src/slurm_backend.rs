@@ -0,0 +1,155 @@
+use std::io::{ErrorKind, Read, Write};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// How a SlurmManager reaches SLURM, e.g. on this machine or on a remote login
+// node. Implementations only need to run a SLURM program, the manager builds
+// every command (including --clusters) itself and passes it to run.
+pub trait SlurmBackend: Send + Sync {
+    // runs `program` (sbatch, squeue, sacct, scancel, scontrol, ...) with `args` and
+    // passes `input` on stdin, sbatch reads the script from there
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> std::io::Result<Output>;
+
+    // backends that spawn processes should kill them after `timeout`, see
+    // DEFAULT_COMMAND_TIMEOUT
+    fn set_command_timeout(&mut self, _timeout: Duration) {}
+}
+
+// site or user defaults for the columns squeue and sacct print, the manager
+// always passes its own --format so they are removed for every command
+pub(crate) const FORMAT_OVERRIDES: [&str; 3] = ["SQUEUE_FORMAT", "SQUEUE_FORMAT2", "SACCT_FORMAT"];
+
+// how long a SLURM command may take before it is killed, a hanging
+// controller would otherwise block the manager forever
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+// runs the command to completion, a program that exits without reading all
+// of its input is not an error. A command still running after `timeout` is
+// killed and reported as TimedOut.
+pub(crate) fn output_with_input(
+    mut command: Command,
+    input: Option<&str>,
+    timeout: Duration,
+) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.unwrap_or_default().to_string();
+        thread::spawn(move || match stdin.write_all(input.as_bytes()) {
+            Err(why) if why.kind() != ErrorKind::BrokenPipe => Err(why),
+            _ => Ok(()),
+        })
+    });
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let status = match wait_with_timeout(&mut child, timeout)? {
+        Some(status) => status,
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                format!("{:?} did not finish within {:?}", command, timeout),
+            ));
+        }
+    };
+    if let Some(writer) = writer {
+        writer.join().expect("stdin writer panicked")?;
+    }
+    Ok(Output {
+        status,
+        stdout: stdout.join().expect("stdout reader panicked"),
+        stderr: stderr.join().expect("stderr reader panicked"),
+    })
+}
+
+// pipes are drained while the child runs, so a chatty child cannot block on
+// a full pipe
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+// None if the child is still running once the timeout is over
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_with_input_feeds_stdin() {
+        let output = output_with_input(
+            Command::new("cat"),
+            Some("#!/bin/bash\n"),
+            DEFAULT_COMMAND_TIMEOUT,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "#!/bin/bash\n");
+    }
+
+    #[test]
+    fn output_with_input_ignores_unread_input() {
+        let output = output_with_input(
+            Command::new("true"),
+            Some(&"x".repeat(1 << 20)),
+            DEFAULT_COMMAND_TIMEOUT,
+        )
+        .unwrap();
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn output_with_input_reports_missing_program() {
+        assert!(
+            output_with_input(
+                Command::new("/nonexistent/sbatch"),
+                Some(""),
+                DEFAULT_COMMAND_TIMEOUT
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn output_with_input_collects_large_output() {
+        let mut command = Command::new("head");
+        command.args(["-c", "1000000", "/dev/zero"]);
+        let output = output_with_input(command, None, DEFAULT_COMMAND_TIMEOUT).unwrap();
+        assert_eq!(output.stdout.len(), 1_000_000);
+    }
+
+    #[test]
+    fn output_with_input_kills_hanging_command() {
+        let mut command = Command::new("sleep");
+        command.arg("10");
+        let started = Instant::now();
+        let why = output_with_input(command, None, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(why.kind(), ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,498 +1,4881 @@
 use crate::job::SlurmJob;
+use crate::job_bucket::JobBucket;
+use crate::job_builder::SlurmJobBuilder;
+use crate::job_handle::JobHandle;
+use crate::job_post_processing::SlurmJobPostProcessing;
+use crate::job_state::SlurmJobState;
 use crate::job_status::SlurmJobStatus;
-use crate::job_status::SlurmJobStatus::{PENDING, SUBMITTED};
-use chrono::{Local, TimeDelta};
+use crate::job_status::SlurmJobStatus::{
+    CANCELLED, CRASHED, FAILED, FINISHED, HELD, PENDING, QUEUED, RUNNING, SKIPPED, SUBMITTED,
+    TIMEOUT,
+};
+use crate::job_usage::JobUsage;
+use crate::local_backend::LocalBackend;
+use crate::manager_summary::Summary;
+use crate::memory_size::Memory;
+use crate::slurm_backend::{DEFAULT_COMMAND_TIMEOUT, SlurmBackend};
+use crate::tick_result::TickResult;
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
 use log::{error, info, warn};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Output;
 use std::thread;
 use std::time::Duration;
 
+// Everything that can go wrong while talking to SLURM. SlurmUnresponsive
+// (and BadSacctResponse) usually go away on their own, see is_transient.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlurmInteractionError {
+    // sbatch's stderr explains rejections such as "Invalid account"
+    BadSbatchResponse {
+        stdout: String,
+        stderr: String,
+        exit_code: Option<i32>,
+    },
+    BadScancelResponse(String),
+    BadScontrolResponse(String),
+    BadSacctResponse(String),
+    // squeue's stderr, e.g. for an unknown partition or job id
+    BadSqueueResponse(String),
+    BadSqueueRow(String),
+    SlurmUnresponsive(String),
+    ScriptNotWritten(String),
+    UnknownJob(i32),
+    UnknownHandle(JobHandle),
+    // only crashed, failed and timed out jobs can be requeued
+    JobNotFailed(JobHandle),
+}
+
+impl SlurmInteractionError {
+    // the variant name, logged as the `error_kind` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SlurmInteractionError::BadSbatchResponse { .. } => "BadSbatchResponse",
+            SlurmInteractionError::BadScancelResponse(_) => "BadScancelResponse",
+            SlurmInteractionError::BadScontrolResponse(_) => "BadScontrolResponse",
+            SlurmInteractionError::BadSacctResponse(_) => "BadSacctResponse",
+            SlurmInteractionError::BadSqueueResponse(_) => "BadSqueueResponse",
+            SlurmInteractionError::BadSqueueRow(_) => "BadSqueueRow",
+            SlurmInteractionError::SlurmUnresponsive(_) => "SlurmUnresponsive",
+            SlurmInteractionError::ScriptNotWritten(_) => "ScriptNotWritten",
+            SlurmInteractionError::UnknownJob(_) => "UnknownJob",
+            SlurmInteractionError::UnknownHandle(_) => "UnknownHandle",
+            SlurmInteractionError::JobNotFailed(_) => "JobNotFailed",
+        }
+    }
+
+    // SLURM could not be reached or its accounting database did not answer,
+    // backing off and trying again later is likely to help. Other errors
+    // need a change to the job or the call.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            SlurmInteractionError::SlurmUnresponsive(_)
+                | SlurmInteractionError::BadSacctResponse(_)
+        )
+    }
+}
+
+impl Display for SlurmInteractionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SlurmInteractionError::BadSbatchResponse {
+                stdout,
+                stderr,
+                exit_code,
+            } => {
+                let reason = if stderr.is_empty() { stdout } else { stderr };
+                match exit_code {
+                    Some(exit_code) => write!(
+                        f,
+                        "sbatch did not return a job number (exit code {}): {}",
+                        exit_code, reason
+                    ),
+                    None => write!(f, "sbatch was killed by a signal: {}", reason),
+                }
+            }
+            SlurmInteractionError::BadScancelResponse(why) => write!(f, "scancel failed: {}", why),
+            SlurmInteractionError::BadScontrolResponse(why) => {
+                write!(f, "scontrol failed: {}", why)
+            }
+            SlurmInteractionError::BadSacctResponse(why) => write!(f, "sacct failed: {}", why),
+            SlurmInteractionError::BadSqueueResponse(why) => write!(f, "squeue failed: {}", why),
+            SlurmInteractionError::BadSqueueRow(row) => {
+                write!(f, "could not parse squeue row: {}", row)
+            }
+            SlurmInteractionError::SlurmUnresponsive(why) => {
+                write!(f, "SLURM did not respond: {}", why)
+            }
+            SlurmInteractionError::ScriptNotWritten(why) => {
+                write!(f, "could not write the job script: {}", why)
+            }
+            SlurmInteractionError::UnknownJob(number) => {
+                write!(f, "no scheduled job has the number {}", number)
+            }
+            SlurmInteractionError::UnknownHandle(handle) => {
+                write!(f, "no job has the handle {:?}", handle)
+            }
+            SlurmInteractionError::JobNotFailed(handle) => {
+                write!(f, "job {:?} did not fail and cannot be rerun", handle)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SlurmInteractionError {}
+
+// One row of `squeue --format "%.i %.P %.j %.u %.t %.M %.D %R"`.
 #[derive(Debug)]
-enum SlurmInteractionError {
-    BadSbatchResponse(#[allow(unused)] String),
-    SlurmUnresponsive(#[allow(unused)] String),
+struct SqueueRow {
+    number: i32,
+    #[allow(unused)]
+    partition: String,
+    #[allow(unused)]
+    name: String,
+    #[allow(unused)]
+    user: String,
+    state: String,
+    #[allow(unused)]
+    time: String,
+    #[allow(unused)]
+    nodes: i32,
+    reason: String,
+}
+
+// What save_state writes to disk, settings other than max_queue are not kept.
+// total_submissions is kept so a resumed manager still honours
+// max_total_submissions, state files written before it count from 0.
+#[derive(Serialize, Deserialize)]
+struct ManagerState {
+    max_queue: i32,
+    next_handle: usize,
+    #[serde(default)]
+    total_submissions: u32,
+    open_jobs: VecDeque<SlurmJob>,
+    scheduled_jobs: Vec<SlurmJob>,
+    finished_jobs: Vec<SlurmJob>,
 }
 
+// kept scripts are only readable by their owner unless set_script_mode says
+// otherwise
+const DEFAULT_SCRIPT_MODE: u32 = 0o600;
+
+type StatusChangeCallback = Box<dyn Fn(&SlurmJob, SlurmJobStatus) + Send + Sync>;
+type ScriptTransform = Box<dyn Fn(String) -> String + Send + Sync>;
+
 pub struct SlurmManager {
-    open_jobs: Vec<SlurmJob>,
+    open_jobs: VecDeque<SlurmJob>,
     scheduled_jobs: Vec<SlurmJob>,
     finished_jobs: Vec<SlurmJob>,
     max_queue: i32,
+    poll_interval: Duration,
+    max_retries: u32,
+    absent_polls: u32,
+    accounting_polls: u32,
+    job_timeout: Option<Duration>,
+    max_total_cpus: Option<usize>,
+    max_total_memory: Option<Memory>,
+    default_job_memory: Option<Memory>,
+    next_handle: usize,
+    backend: Box<dyn SlurmBackend>,
+    cluster: Option<String>,
+    keep_scripts: bool,
+    script_mode: u32,
+    log_dir: Option<PathBuf>,
+    modules: Vec<String>,
+    env: HashMap<String, String>,
+    command_timeout: Duration,
+    submit_concurrency: usize,
+    max_total_submissions: Option<u32>,
+    total_submissions: u32,
+    // the limit warning is logged once, when open jobs are first held back
+    submission_limit_warned: bool,
+    draining: bool,
+    on_status_change: Option<StatusChangeCallback>,
+    script_transform: Option<ScriptTransform>,
 }
 
 impl SlurmManager {
     pub fn new(max_queue: i32) -> SlurmManager {
         SlurmManager {
-            open_jobs: Vec::new(),
+            open_jobs: VecDeque::new(),
             scheduled_jobs: Vec::new(),
             finished_jobs: Vec::new(),
             max_queue,
+            poll_interval: Duration::from_secs(5),
+            max_retries: 3,
+            absent_polls: 1,
+            accounting_polls: 3,
+            job_timeout: None,
+            max_total_cpus: None,
+            max_total_memory: None,
+            default_job_memory: None,
+            next_handle: 0,
+            backend: Box::new(LocalBackend::new()),
+            cluster: None,
+            keep_scripts: false,
+            script_mode: DEFAULT_SCRIPT_MODE,
+            log_dir: None,
+            modules: Vec::new(),
+            env: HashMap::new(),
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+            submit_concurrency: 1,
+            max_total_submissions: None,
+            total_submissions: 0,
+            submission_limit_warned: false,
+            draining: false,
+            on_status_change: None,
+            script_transform: None,
         }
     }
 
-    pub fn add_job(&mut self, job: &SlurmJob) {
-        let mut cloned = job.clone();
-        cloned.set_status(PENDING);
-        self.open_jobs.push(cloned);
+    // a manager reaching SLURM through the given backend, e.g. an
+    // SshBackend or a MockScheduler in tests
+    pub fn with_backend(max_queue: i32, backend: impl SlurmBackend + 'static) -> SlurmManager {
+        let mut manager = SlurmManager::new(max_queue);
+        manager.set_backend(backend);
+        manager
     }
 
-    #[allow(unused)]
-    pub fn add_jobs(&mut self, jobs: Vec<SlurmJob>) {
-        jobs.iter().for_each(|job| self.add_job(job))
+    // how long manage_jobs waits between two checks on the queue
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        assert!(
+            !poll_interval.is_zero(),
+            "poll interval must be greater than zero"
+        );
+        self.poll_interval = poll_interval;
     }
 
-    pub fn successful_jobs(&self) -> i32 {
-        self.finished_jobs
-            .iter()
-            .filter(|job| job.get_status() == SlurmJobStatus::FINISHED)
-            .count() as i32
+    // how often a job that could not be submitted is retried before it is
+    // marked as FAILED
+    pub fn set_max_retries(&mut self, max_retries: u32) {
+        self.max_retries = max_retries;
     }
 
-    fn parse_squeue_row(row: &str) -> (i32, String, String, String, String, String, i32, String) {
-        let row_split: Vec<&str> = row.split(" ").collect();
-        if row_split.len() != 8 {
-            panic!("unexpected row format: {}", row);
-        }
-        (
-            //todo: we should also support arrays but do not do so yet
-            row_split[0]
-                .parse()
-                .expect(format!("we need an integer at the first element: {}", row).as_str()),
-            String::from(row_split[1]),
-            String::from(row_split[2]),
-            String::from(row_split[3]),
-            String::from(row_split[4]),
-            String::from(row_split[5]),
-            row_split[6]
-                .parse()
-                .expect(format!("we need an integer at the sixth element: {}", row).as_str()),
-            String::from(row_split[7]),
-        )
+    // how many consecutive polls a job has to be missing from squeue before
+    // it is considered done, 1 by default. A higher value guards against
+    // requeued jobs that briefly vanish when sacct cannot tell.
+    pub fn set_absent_polls(&mut self, absent_polls: u32) {
+        assert!(absent_polls > 0, "absent_polls must be at least 1");
+        self.absent_polls = absent_polls;
     }
 
-    fn get_running_jobs(&self) -> Result<HashSet<i32>, SlurmInteractionError> {
-        let mut running_jobs: HashSet<i32> = HashSet::new();
-        match std::process::Command::new("squeue")
-            .args(["--me", "--format", "%.i %.P %.j %.u %.t %.M %.D %R"])
-            .output()
-        {
-            Ok(output) => {
-                let out = String::from_utf8(output.stdout).expect("squeue should return string");
-                let split: Vec<&str> = out.split("\n").collect();
-                for row in &split[1..] {
-                    if row.len() == 0 {
-                        continue;
-                    }
-                    let (id, _, _, _, _, _, _, _) = Self::parse_squeue_row(row);
-                    running_jobs.insert(id);
-                }
-                Result::Ok(running_jobs)
-            }
-            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
-        }
+    // how many polls sacct may have no record of a job that left the queue
+    // before the post-processing alone decides how it ended, 3 by default.
+    // Accounting records can show up a little after the job left squeue.
+    pub fn set_accounting_polls(&mut self, accounting_polls: u32) {
+        assert!(accounting_polls > 0, "accounting_polls must be at least 1");
+        self.accounting_polls = accounting_polls;
     }
 
-    fn check_on_jobs(&mut self) -> Result<i32, SlurmInteractionError> {
-        let running_jobs = self.get_running_jobs()?;
-        let mut finished_jobs = 0;
-        let mut done = Vec::new();
-        for (index, job) in self.scheduled_jobs.iter().enumerate() {
-            if !running_jobs.contains(&job.get_number()) {
-                done.push(index);
-                finished_jobs += 1;
-            }
-        }
-        done.sort_by(|a, b| a.cmp(b));
-        done.reverse();
-        for elem in done {
-            let mut finished_job = self.scheduled_jobs.remove(elem);
-            let status = finished_job.run_post_processing();
-            finished_job.set_status(status);
-            self.finished_jobs.push(finished_job);
-        }
-        Result::Ok(finished_jobs)
+    // jobs submitted longer ago than this, whether queued or running, are
+    // cancelled and marked as TIMEOUT. A timeout too large for a date, e.g.
+    // Duration::MAX, never cancels a job.
+    pub fn set_job_timeout(&mut self, job_timeout: Duration) {
+        assert!(!job_timeout.is_zero(), "job timeout must not be zero");
+        self.job_timeout = Some(job_timeout);
     }
 
-    fn schedule_job(&self, job: &mut SlurmJob) -> Result<i32, SlurmInteractionError> {
-        let tmp_dir = match std::env::var("TMP_DIR") {
-            Ok(tmp_dir) => tmp_dir,
-            _ => String::from("/tmp/"),
-        };
-        let slurm_script = tmp_dir + "script.slurm";
-        let mut slurm_file = File::create(&slurm_script).expect("Couldn't create slurm script");
-        slurm_file
-            .write(job.generate_slurm_script().as_bytes())
-            .expect("Couldn't write to slurm script");
-        slurm_file.flush().expect("Couldn't flush slurm script");
-        slurm_file.sync_all().expect("Couldn't sync slurm script");
-        match std::process::Command::new("sbatch")
-            .arg(slurm_script)
-            .output()
+    // no job is submitted while it would push the CPUs of all scheduled jobs
+    // above this limit, even if max_queue leaves room
+    pub fn set_max_total_cpus(&mut self, max_total_cpus: usize) {
+        self.max_total_cpus = Some(max_total_cpus);
+    }
+
+    // like set_max_total_cpus for the memory of all scheduled jobs
+    pub fn set_max_total_memory(&mut self, max_total_memory: Memory) {
+        self.max_total_memory = Some(max_total_memory);
+    }
+
+    // memory per node counted against set_max_total_memory for jobs without
+    // a memory request, e.g. the DefMemPerNode of the partition. Without it
+    // such jobs count as 0 and add_job warns about them.
+    pub fn set_default_job_memory(&mut self, default_job_memory: Memory) {
+        self.default_job_memory = Some(default_job_memory);
+    }
+
+    fn default_job_megabytes(&self) -> u64 {
+        self.default_job_memory
+            .as_ref()
+            .map_or(0, Memory::as_megabytes)
+    }
+
+    // a job without a memory request slips through the memory budget unless
+    // a default job memory is set
+    fn warn_unbudgeted_memory(&self, job: &SlurmJob) {
+        if self.max_total_memory.is_some()
+            && self.default_job_memory.is_none()
+            && !job.requests_memory()
         {
-            Ok(output) => {
-                let mut out =
-                    String::from_utf8(output.stdout).expect("Couldn't convert output to string");
-                out = out.trim().to_string();
-                let out_split = out.split(" ").collect::<Vec<&str>>();
-                match out_split.last().unwrap().parse::<i32>() {
-                    Ok(job_id) => {
-                        job.set_status(SUBMITTED);
-                        Ok(job_id)
-                    }
-                    Err(_) => Err(SlurmInteractionError::BadSbatchResponse(String::from(out))),
-                }
-            }
-            Err(bad_status) => Err(SlurmInteractionError::SlurmUnresponsive(
-                bad_status.to_string(),
-            )),
+            warn!(
+                job_id = job.get_id().as_str();
+                "job {} requests no memory and does not count against the memory budget, \
+                 set a default job memory to count it",
+                job
+            );
         }
     }
 
-    fn fill_up_queue(&mut self) -> Result<i32, Vec<SlurmInteractionError>> {
-        let mut errors = Vec::<SlurmInteractionError>::new();
-        let queue_delta = self.max_queue - self.scheduled_jobs.len() as i32;
-        let mut added_jobs = 0;
-        for _ in 0..queue_delta {
-            match self.open_jobs.pop() {
-                Some(mut job) => match self.schedule_job(&mut job) {
-                    Ok(job_id) => {
-                        job.set_number(job_id);
-                        self.scheduled_jobs.push(job);
-                        added_jobs += 1;
-                    }
-                    Err(e) => {
-                        error!("encountered issue {:?}", e);
-                        errors.push(e);
-                    }
-                },
-                None => return Ok(added_jobs),
-            }
-        }
-        if errors.len() == 0 {
-            Ok(added_jobs)
+    // how many sbatch calls may run at the same time while filling up the
+    // queue, 1 (one after the other) by default
+    pub fn set_submit_concurrency(&mut self, submit_concurrency: usize) {
+        assert!(
+            submit_concurrency > 0,
+            "submit concurrency must be at least 1"
+        );
+        self.submit_concurrency = submit_concurrency;
+    }
+
+    // safety valve against flooding the scheduler: once this many jobs were
+    // submitted over the lifetime of the manager, open jobs stay open as if
+    // drain was called. submit_now is counted but never refused.
+    pub fn set_max_total_submissions(&mut self, max_total_submissions: u32) {
+        self.max_total_submissions = Some(max_total_submissions);
+        self.submission_limit_warned = false;
+    }
+
+    fn submission_limit_reached(&self) -> bool {
+        self.max_total_submissions
+            .is_some_and(|max_total_submissions| self.total_submissions >= max_total_submissions)
+    }
+
+    // open jobs that will not be submitted because max_total_submissions was
+    // reached
+    fn held_back_jobs(&self) -> usize {
+        if self.submission_limit_reached() {
+            self.open_jobs.len()
         } else {
-            Err(errors)
+            0
         }
     }
 
-    // start scheduling jobs, return true if all jobs are done
-    pub fn manage_jobs(&mut self, for_sec: Option<i64>) -> bool {
-        let max_time_delta = 365 * 24 * 60; // one year worth of seconds
-        let end_time = Local::now() + TimeDelta::seconds(for_sec.unwrap_or_else(|| max_time_delta));
-        loop {
-            // run loop until either the time is up
-            if Local::now() >= end_time
-                || (self.open_jobs.is_empty() && self.scheduled_jobs.is_empty())
-            {
-                break;
-            }
-            match self.check_on_jobs() {
-                Result::Ok(finished_jobs) => {
-                    info!("jobs finished since last check {}", finished_jobs);
-                }
-                Result::Err(why) => {
-                    warn!("Error while checking on jobs: {:?}", why);
-                }
-            }
-            match self.fill_up_queue() {
-                Result::Ok(added_jobs) => {
-                    if added_jobs > 0 {
-                        info!("we scheduled {} new jobs", added_jobs);
-                    }
-                }
-                Result::Err(why) => {
-                    for e in &why {
-                        error!("scheduling error: {:?}", e);
-                    }
-                    error!("while scheduling jobs we encountered {} errors", why.len());
-                }
-            }
-            let time_remaining = end_time - Local::now();
-            info!(
-                "there are {} jobs remaining to be completed within the next {} seconds",
-                self.open_jobs.len() + self.scheduled_jobs.len(),
-                time_remaining.as_seconds_f32()
-            );
-            thread::sleep(Duration::from_secs(5)); // wait for 5 seconds and then update jobs
+    fn warn_submission_limit(&mut self) {
+        if self.submission_limit_warned || self.held_back_jobs() == 0 {
+            return;
         }
-        self.open_jobs.is_empty() && self.scheduled_jobs.is_empty()
+        warn!(
+            total_submissions = self.total_submissions, open_jobs = self.open_jobs.len();
+            "reached the limit of {} submitted jobs, {} open jobs will not be submitted",
+            self.total_submissions,
+            self.open_jobs.len()
+        );
+        self.submission_limit_warned = true;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    //use crate::logging::Logger;
-    use super::*;
-    use crate::job_builder::SlurmJobBuilder;
-    use crate::job_post_processing::SlurmJobPostProcessing;
-    use serial_test::serial;
+    // directory containing sbatch, squeue, sacct and scancel, by default they
+    // are looked up on PATH. Replaces the backend with a local one.
+    pub fn set_binary_path(&mut self, binary_path: PathBuf) {
+        self.set_backend(LocalBackend::with_binary_path(binary_path));
+    }
 
-    fn init_logger() {
-        //todo: do we need to init anything here?
+    // how SLURM is reached, e.g. an SshBackend for a remote login node,
+    // LocalBackend by default. The backend uses the manager's command timeout.
+    pub fn set_backend(&mut self, backend: impl SlurmBackend + 'static) {
+        self.backend = Box::new(backend);
+        self.backend.set_command_timeout(self.command_timeout);
     }
 
-    fn sleep_job(wdir: Option<String>) -> SlurmJob {
-        let job = SlurmJobBuilder::new(String::from("sleep 5"))
-            .set_description(String::from("sleeps for 5 seconds"));
-        match wdir {
-            Some(dir) => job.set_working_directory(String::from(dir)).build(),
-            None => job.build(),
-        }
+    // sbatch, squeue, ... calls taking longer are killed and reported as
+    // SlurmUnresponsive, 30 seconds by default. An sbatch killed after the
+    // controller accepted the job is still retried, which submits the job a
+    // second time, so keep the timeout well above the slowest sbatch.
+    pub fn set_command_timeout(&mut self, command_timeout: Duration) {
+        assert!(
+            !command_timeout.is_zero(),
+            "command timeout must not be zero"
+        );
+        self.command_timeout = command_timeout;
+        self.backend.set_command_timeout(command_timeout);
     }
 
-    // Unique path for a file a job can `touch` as a side-effect marker of
-    // having actually completed its command (as opposed to being killed
-    // by SLURM for exceeding a time or memory limit).
-    fn marker_path() -> String {
-        let tmp_dir = std::env::var("TMP_DIR").unwrap_or_else(|_| String::from("/tmp/"));
-        format!("{}marker_{}", tmp_dir, uuid::Uuid::new_v4())
+    // keeps a copy of every submitted script in TMP_DIR for post-mortem
+    // inspection, sbatch itself reads the script from stdin
+    pub fn set_keep_scripts(&mut self, keep_scripts: bool) {
+        self.keep_scripts = keep_scripts;
     }
 
-    fn marker_exists(path: &str) -> bool {
-        std::path::Path::new(path).exists()
+    // unix permissions of kept scripts, 0o600 by default. Ignored on other
+    // platforms.
+    pub fn set_script_mode(&mut self, script_mode: u32) {
+        assert!(
+            script_mode <= 0o777,
+            "script mode must be at most 0o777, got: {:o}",
+            script_mode
+        );
+        self.script_mode = script_mode;
     }
 
-    // Post-processing that succeeds only if the job actually ran its
-    // command to completion and left the marker file behind.
-    fn marker_post_processing(marker: &str) -> SlurmJobPostProcessing {
-        SlurmJobPostProcessing::new(&[("marker".to_string(), marker.to_string())], |params| {
-            std::path::Path::new(&params["marker"]).exists()
-        })
+    // jobs without an output or error file write them to <log_dir>/<number>.out
+    // and <log_dir>/<number>.err instead of discarding them. The directory
+    // has to exist on the cluster, SLURM does not create it.
+    pub fn set_log_dir(&mut self, log_dir: PathBuf) {
+        self.log_dir = Some(log_dir);
     }
 
-    #[test]
-    fn generate_job_command() {
-        let job = sleep_job(None);
-        assert_eq!(job.generate_slurm_commands(), "sleep 5\n");
+    // loaded in every job before the job's own modules
+    pub fn add_module(&mut self, module: String) {
+        self.modules.push(module);
     }
 
-    #[test]
-    fn generate_job_command_wdir() {
-        let job = sleep_job(Some("/tmp/".parse().unwrap()));
-        assert_eq!(
-            job.generate_slurm_commands(),
-            r"pushd /tmp/
-sleep 5
-popd
-"
+    // exported in every job, a job setting the same variable keeps its own
+    // value
+    pub fn add_env(&mut self, key: String, value: String) {
+        assert!(
+            SlurmJobBuilder::check_env_key(&key),
+            "invalid environment variable name: {}",
+            key
         );
+        self.env.insert(key, value);
     }
 
-    #[test]
-    fn generate_full_script() {
-        let job = sleep_job(None);
-        let mut expected: String = String::from("#!/bin/bash\n");
-        expected += format!("#SBATCH --job-name={}\n", job.get_id()).as_str();
-        expected += "#SBATCH --output=/dev/null\n";
-        expected += "#SBATCH --error=/dev/null\n";
-        expected += "#SBATCH --cpus-per-task=1\n";
-        expected += "#SBATCH --mem=100M\n";
-        expected += "\n\n";
-        expected += "echo START: `date +%Y-%m-%dT%H:%M:%S%z`\n";
-        expected += "sleep 5\n";
-        expected += "\necho END: `date +%Y-%m-%dT%H:%M:%S%z`\n";
-        assert_eq!(job.generate_slurm_script(), expected);
+    // called with the job and its new status whenever the manager changes the
+    // status of a job
+    pub fn set_on_status_change(
+        &mut self,
+        on_status_change: impl Fn(&SlurmJob, SlurmJobStatus) + Send + Sync + 'static,
+    ) {
+        self.on_status_change = Some(Box::new(on_status_change));
     }
 
-    #[test]
-    fn generate_full_script_with_all_options() {
-        use crate::memory_size::Memory::GigaByte;
-        let job = SlurmJobBuilder::new(String::from("sleep 5"))
-            .set_output_file("out.log".to_string())
-            .set_error_file("err.log".to_string())
-            .set_cpus(4)
-            .set_memory(GigaByte(8))
-            .set_max_run_time("1-02:30:00".to_string())
-            .set_working_directory("/tmp/".to_string())
-            .build();
-        let script = job.generate_slurm_script();
-        assert!(script.contains("#SBATCH --output=out.log\n"));
-        assert!(script.contains("#SBATCH --error=err.log\n"));
-        assert!(script.contains("#SBATCH --cpus-per-task=4\n"));
-        assert!(script.contains("#SBATCH --mem=8G\n"));
-        assert!(script.contains("#SBATCH --time=1-02:30:00\n"));
-        assert!(script.contains("pushd /tmp/\n"));
-        assert!(script.contains("popd\n"));
+    // receives every generated script right before it is submitted and
+    // returns the text passed to sbatch, e.g. to wrap the command in a
+    // profiler. Kept scripts contain the transformed text.
+    pub fn set_script_transform(
+        &mut self,
+        script_transform: impl Fn(String) -> String + Send + Sync + 'static,
+    ) {
+        self.script_transform = Some(Box::new(script_transform));
     }
 
-    #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn create_and_run_jobs() {
-        let job = sleep_job(None);
-        init_logger();
-        let mut manager = SlurmManager::new(2);
-        manager.add_job(&job);
-        let pre_start = manager.check_on_jobs().expect("Should have checked no job");
-        let scheduled = manager.fill_up_queue().expect("Couldn't fill up queue");
-        let running = manager.get_running_jobs().expect("get running jobs").len();
-        let done = manager.manage_jobs(Some(20));
-        assert_eq!(pre_start, 0);
-        assert_eq!(scheduled, 1);
-        assert_eq!(running, 1);
-        assert!(done);
+    // runs every SLURM command with --clusters=<cluster>, for federations with
+    // several clusters. Commands use the default cluster if unset.
+    pub fn set_cluster(&mut self, cluster: String) {
+        self.cluster = Some(cluster);
     }
 
-    #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn create_and_run_multiple_jobs() {
-        let job_one = sleep_job(None);
-        let job_two = sleep_job(None);
-        init_logger();
-        let mut manager = SlurmManager::new(2);
-        manager.add_jobs(Vec::from([job_one, job_two]));
-        let pre_start = manager.check_on_jobs().expect("Should have checked no job");
-        let scheduled = manager.fill_up_queue().expect("Couldn't fill up queue");
-        let running = manager.get_running_jobs().expect("get running jobs").len();
-        let done = manager.manage_jobs(Some(20));
-        assert_eq!(pre_start, 0);
-        assert_eq!(scheduled, 2);
-        assert_eq!(running, 2);
-        assert!(done);
+    // all SLURM commands of the manager go through here so that they target
+    // the selected cluster
+    fn run_slurm(
+        &self,
+        program: &str,
+        args: &[String],
+        input: Option<&str>,
+    ) -> std::io::Result<Output> {
+        match self.cluster {
+            Some(ref cluster) => {
+                let mut cluster_args = vec![format!("--clusters={}", cluster)];
+                cluster_args.extend_from_slice(args);
+                self.backend.run(program, &cluster_args, input)
+            }
+            None => self.backend.run(program, args, input),
+        }
     }
 
-    #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn manage_jobs_returns_false_when_time_runs_out() {
-        // sleep 30 won't finish within the 5-second budget
-        let job = SlurmJobBuilder::new(String::from("sleep 30")).build();
-        let mut manager = SlurmManager::new(1);
-        manager.add_job(&job);
-        let all_done = manager.manage_jobs(Some(5));
-        assert!(
-            !all_done,
-            "manage_jobs should return false when the time limit expires before all jobs finish"
-        );
-        assert!(
-            !(manager.open_jobs.is_empty() && manager.scheduled_jobs.is_empty()),
-            "the unfinished job should still be tracked (open or scheduled), not silently dropped"
+    fn transition(&self, job: &mut SlurmJob, status: SlurmJobStatus) {
+        if status == RUNNING && job.started_at.is_none() {
+            job.started_at = Some(Local::now());
+        }
+        if status.is_final() {
+            job.finished_at = Some(Local::now());
+        }
+        info!(
+            job_id = job.get_id().as_str(), job_number = job.number, status:% = status;
+            "job {} is now {}", job, status
         );
-        // the job is intentionally left running by this test; cancel it so it
-        // doesn't linger in squeue and pollute subsequent tests
-        for job in &manager.scheduled_jobs {
-            let _ = std::process::Command::new("scancel")
-                .arg(job.get_number().to_string())
-                .output();
+        job.set_status(status.clone());
+        if let Some(ref on_status_change) = self.on_status_change {
+            on_status_change(job, status);
         }
     }
 
-    #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn slurm_time_limit_kills_job() {
-        // the job would take 30s but is only allowed 5s by SLURM's --time
-        let marker = marker_path();
-        let _ = std::fs::remove_file(&marker);
-        let job = SlurmJobBuilder::new(format!("sleep 30 && touch {}", marker))
-            .set_max_run_time("0-00:00:05".to_string())
-            .set_on_finished(marker_post_processing(&marker))
-            .build();
+    pub fn add_job(&mut self, job: &SlurmJob) -> JobHandle {
+        self.add_job_with_dependency(job, &[])
+    }
+
+    #[allow(unused)]
+    pub fn add_jobs(&mut self, jobs: Vec<SlurmJob>) -> Vec<JobHandle> {
+        jobs.iter().map(|job| self.add_job(job)).collect()
+    }
+
+    // the job is only submitted once all prerequisites are submitted and runs
+    // after they completed successfully (afterok), if a prerequisite does not
+    // finish successfully the job is SKIPPED
+    pub fn add_job_with_dependency(
+        &mut self,
+        job: &SlurmJob,
+        depends_on: &[JobHandle],
+    ) -> JobHandle {
+        for dependency in depends_on {
+            assert!(
+                self.find_job(*dependency).is_some(),
+                "unknown dependency {:?}",
+                dependency
+            );
+        }
+        let handle = JobHandle(self.next_handle);
+        self.next_handle += 1;
+        self.warn_unbudgeted_memory(job);
+        let mut cloned = job.clone();
+        cloned.handle = Some(handle);
+        cloned.dependencies = depends_on.to_vec();
+        self.transition(&mut cloned, PENDING);
+        self.open_jobs.push_back(cloned);
+        handle
+    }
+
+    // submits the job right away, bypassing max_queue, the resource budgets
+    // and drain, and returns its SLURM job number, the job is then tracked
+    // like any other scheduled job, a rejected job is not tracked at all.
+    // Status callbacks see a rejected job go from PENDING to FAILED.
+    pub fn submit_now(&mut self, job: &SlurmJob) -> Result<i32, SlurmInteractionError> {
+        let mut cloned = job.clone();
+        // the handle is used up even if sbatch rejects the job, so callbacks
+        // never see one handle for two jobs
+        cloned.handle = Some(JobHandle(self.next_handle));
+        self.next_handle += 1;
+        self.transition(&mut cloned, PENDING);
+        let job_id = self.schedule_job(&mut cloned).inspect_err(|_| {
+            self.transition(&mut cloned, FAILED);
+        })?;
+        self.total_submissions += 1;
+        cloned.set_number(job_id);
+        cloned.submitted_at = Some(Local::now());
+        self.scheduled_jobs.push(cloned);
+        Ok(job_id)
+    }
+
+    // moves a crashed, failed or timed out job back to the open jobs to run it
+    // again, it keeps its handle but gets a new id and a new SLURM number
+    pub fn rerun_failed(&mut self, handle: JobHandle) -> Result<(), SlurmInteractionError> {
+        let index = self
+            .finished_jobs
+            .iter()
+            .position(|job| job.handle == Some(handle))
+            .ok_or_else(|| match self.find_job(handle) {
+                Some(_) => SlurmInteractionError::JobNotFailed(handle),
+                None => SlurmInteractionError::UnknownHandle(handle),
+            })?;
+        if !self.finished_jobs[index].status().is_failure() {
+            return Err(SlurmInteractionError::JobNotFailed(handle));
+        }
+        let mut job = self.finished_jobs.remove(index);
+        job.reset_for_rerun();
+        self.transition(&mut job, PENDING);
+        self.open_jobs.push_back(job);
+        Ok(())
+    }
+
+    // the SLURM job number, None until the job has been submitted
+    pub fn job_number(&self, handle: JobHandle) -> Option<i32> {
+        self.find_job(handle).and_then(|job| job.number)
+    }
+
+    // why SLURM has not started the scheduled job with this number yet, None
+    // for unknown jobs and jobs that are not pending
+    pub fn job_pending_reason(&self, number: i32) -> Option<&str> {
+        self.scheduled_jobs
+            .iter()
+            .find(|job| job.number == Some(number))
+            .and_then(|job| job.pending_reason())
+    }
+
+    // writes all jobs, their handles, SLURM numbers and statuses as JSON, the
+    // file is replaced atomically so a crash never leaves a truncated state
+    pub fn save_state(&self, path: &Path) -> Result<(), std::io::Error> {
+        let state = ManagerState {
+            max_queue: self.max_queue,
+            next_handle: self.next_handle,
+            total_submissions: self.total_submissions,
+            open_jobs: self.open_jobs.clone(),
+            scheduled_jobs: self.scheduled_jobs.clone(),
+            finished_jobs: self.finished_jobs.clone(),
+        };
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, serde_json::to_vec_pretty(&state)?)?;
+        std::fs::rename(&partial, path)
+    }
+
+    // restores a manager written by save_state, already submitted jobs are
+    // tracked by their SLURM numbers and not submitted again. Post-processing
+    // cannot be stored, post_processing returns it again for each job id,
+    // e.g. SlurmJobPostProcessing::do_nothing() for jobs without checks.
+    pub fn load_state(
+        path: &Path,
+        post_processing: impl Fn(&str) -> SlurmJobPostProcessing,
+    ) -> Result<SlurmManager, std::io::Error> {
+        let state: ManagerState = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut manager = SlurmManager::new(state.max_queue);
+        manager.next_handle = state.next_handle;
+        manager.total_submissions = state.total_submissions;
+        manager.open_jobs = state.open_jobs;
+        manager.scheduled_jobs = state.scheduled_jobs;
+        manager.finished_jobs = state.finished_jobs;
+        for job in manager
+            .open_jobs
+            .iter_mut()
+            .chain(manager.scheduled_jobs.iter_mut())
+            .chain(manager.finished_jobs.iter_mut())
+        {
+            job.on_finished = post_processing(job.get_id());
+        }
+        Ok(manager)
+    }
+
+    // replaces the post-processing of any job the manager knows, e.g. the
+    // check load_state attached, it runs once the job finishes next
+    pub fn set_on_finished(
+        &mut self,
+        handle: JobHandle,
+        on_finished: SlurmJobPostProcessing,
+    ) -> Result<(), SlurmInteractionError> {
+        let job = self
+            .open_jobs
+            .iter_mut()
+            .chain(self.scheduled_jobs.iter_mut())
+            .chain(self.finished_jobs.iter_mut())
+            .find(|job| job.handle == Some(handle))
+            .ok_or(SlurmInteractionError::UnknownHandle(handle))?;
+        job.on_finished = on_finished;
+        Ok(())
+    }
+
+    fn find_job(&self, handle: JobHandle) -> Option<&SlurmJob> {
+        self.open_jobs
+            .iter()
+            .chain(self.scheduled_jobs.iter())
+            .chain(self.finished_jobs.iter())
+            .find(|job| job.handle == Some(handle))
+    }
+
+    // SLURM numbers of the prerequisites still in the queue, None while a
+    // prerequisite has not been submitted or did not finish successfully
+    fn pending_dependencies(&self, job: &SlurmJob) -> Option<Vec<i32>> {
+        let mut numbers = Vec::new();
+        for dependency in &job.dependencies {
+            let prerequisite = self.find_job(*dependency)?;
+            match prerequisite.number {
+                Some(number)
+                    if self
+                        .scheduled_jobs
+                        .iter()
+                        .any(|j| j.handle == Some(*dependency)) =>
+                {
+                    numbers.push(number)
+                }
+                _ if prerequisite.status() == FINISHED => {}
+                _ => return None,
+            }
+        }
+        Some(numbers)
+    }
+
+    fn has_failed_dependency(&self, job: &SlurmJob) -> bool {
+        job.dependencies.iter().any(|dependency| {
+            self.finished_jobs
+                .iter()
+                .any(|j| j.handle == Some(*dependency) && j.status() != FINISHED)
+        })
+    }
+
+    pub fn successful_jobs(&self) -> i32 {
+        self.finished_jobs
+            .iter()
+            .filter(|job| job.status() == SlurmJobStatus::FINISHED)
+            .count() as i32
+    }
+
+    // every job the manager is done with, whatever the outcome
+    pub fn finished_jobs(&self) -> &[SlurmJob] {
+        &self.finished_jobs
+    }
+
+    // jobs that crashed while running or could not be submitted at all
+    pub fn failed_jobs(&self) -> Vec<&SlurmJob> {
+        self.finished_jobs
+            .iter()
+            .filter(|job| job.status().is_failure())
+            .collect()
+    }
+
+    // every job the manager knows about with the bucket it is in: open jobs
+    // in submission order, then scheduled and finished jobs
+    pub fn jobs(&self) -> impl Iterator<Item = (&SlurmJob, JobBucket)> {
+        let open = self.open_jobs.iter().map(|job| (job, JobBucket::Open));
+        let scheduled = self
+            .scheduled_jobs
+            .iter()
+            .map(|job| (job, JobBucket::Scheduled));
+        let finished = self
+            .finished_jobs
+            .iter()
+            .map(|job| (job, JobBucket::Finished));
+        open.chain(scheduled).chain(finished)
+    }
+
+    // counts of open, scheduled and finished jobs, e.g. to show progress
+    // between two manage_jobs calls
+    pub fn summary(&self) -> Summary {
+        let finished = self.finished_jobs.len();
+        Summary {
+            open: self.open_jobs.len(),
+            scheduled: self.scheduled_jobs.len(),
+            held: self
+                .scheduled_jobs
+                .iter()
+                .filter(|job| job.status() == HELD)
+                .count(),
+            finished,
+            crashed: self.failed_jobs().len(),
+            total: self.open_jobs.len() + self.scheduled_jobs.len() + finished,
+        }
+    }
+
+    // array elements are reported as <job>_<task> or <job>_[<range>] and the
+    // components of a heterogeneous job as <job>+<component>, they are
+    // grouped under the job number sbatch returned
+    fn parse_job_number(field: &str) -> Option<i32> {
+        let base = match field.split_once(['_', '+']) {
+            Some((base, _)) => base,
+            None => field,
+        };
+        base.parse().ok()
+    }
+
+    // the trailing reason column (e.g. "(Resources)") may contain spaces, so
+    // only the first seven columns are split off
+    fn parse_squeue_row(row: &str) -> Result<SqueueRow, SlurmInteractionError> {
+        let bad_row = || SlurmInteractionError::BadSqueueRow(row.to_string());
+        let mut rest = row.trim();
+        let mut fields = Vec::with_capacity(7);
+        for _ in 0..7 {
+            let (field, tail) = rest.split_once(char::is_whitespace).ok_or_else(bad_row)?;
+            fields.push(field);
+            rest = tail.trim_start();
+        }
+        if rest.is_empty() {
+            return Err(bad_row());
+        }
+        Ok(SqueueRow {
+            number: Self::parse_job_number(fields[0]).ok_or_else(bad_row)?,
+            partition: fields[1].to_string(),
+            name: fields[2].to_string(),
+            user: fields[3].to_string(),
+            state: fields[4].to_string(),
+            time: fields[5].to_string(),
+            nodes: fields[6].parse().map_err(|_| bad_row())?,
+            reason: rest.to_string(),
+        })
+    }
+
+    // output of `squeue --noheader`, for arrays and heterogeneous jobs the row
+    // of a running element or component is preferred over pending ones. With --clusters squeue prints a
+    // "CLUSTER: <name>" line first.
+    fn parse_squeue_output(out: &str) -> HashMap<i32, SqueueRow> {
+        let mut running_jobs: HashMap<i32, SqueueRow> = HashMap::new();
+        let rows = out.lines().filter(|row| !row.starts_with("CLUSTER:"));
+        for row in rows {
+            if row.trim().is_empty() {
+                continue;
+            }
+            match Self::parse_squeue_row(row) {
+                Ok(parsed) => {
+                    let known = running_jobs.get(&parsed.number);
+                    if known.is_none_or(|known| known.state != "R") {
+                        running_jobs.insert(parsed.number, parsed);
+                    }
+                }
+                Err(why) => warn!(
+                    error_kind = why.kind();
+                    "skipping unparsable squeue row: {:?}", why
+                ),
+            }
+        }
+        running_jobs
+    }
+
+    // only asks for the jobs this manager submitted, all of the user's jobs
+    // while none are scheduled. The columns are fixed here, the backends drop
+    // SQUEUE_FORMAT and friends from the environment.
+    fn squeue_args(&self) -> Vec<String> {
+        let selection = if self.scheduled_jobs.is_empty() {
+            String::from("--me")
+        } else {
+            let numbers: Vec<String> = self
+                .scheduled_jobs
+                .iter()
+                .map(|job| job.get_number().to_string())
+                .collect();
+            format!("--jobs={}", numbers.join(","))
+        };
+        vec![
+            selection,
+            String::from("--noheader"),
+            String::from("--format"),
+            String::from("%.i %.P %.j %.u %.t %.M %.D %R"),
+        ]
+    }
+
+    // a failing squeue must not be mistaken for an empty queue, every
+    // scheduled job would be looked up in sacct as if it had finished. Only
+    // "Invalid job id" is expected, squeue reports it once SLURM forgot about
+    // all of the requested jobs.
+    fn get_running_jobs(&self) -> Result<HashMap<i32, SqueueRow>, SlurmInteractionError> {
+        match self.run_slurm("squeue", &self.squeue_args(), None) {
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if !output.status.success() && !stderr.contains("Invalid job id") {
+                    return Err(SlurmInteractionError::SlurmUnresponsive(
+                        stderr.trim().to_string(),
+                    ));
+                }
+                let out = String::from_utf8_lossy(&output.stdout);
+                Result::Ok(Self::parse_squeue_output(&out))
+            }
+            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
+        }
+    }
+
+    // rows of `squeue --start --format "%i %S"`, estimates that SLURM cannot
+    // give yet ("N/A") are left out
+    fn parse_start_times(out: &str) -> HashMap<i32, DateTime<Local>> {
+        let mut start_times: HashMap<i32, DateTime<Local>> = HashMap::new();
+        for row in out.lines() {
+            let Some((number, start)) = row.trim().split_once(' ') else {
+                continue;
+            };
+            let start = NaiveDateTime::parse_from_str(start.trim(), "%Y-%m-%dT%H:%M:%S")
+                .ok()
+                .and_then(|start| start.and_local_timezone(Local).earliest());
+            if let (Some(number), Some(start)) = (Self::parse_job_number(number), start) {
+                let earliest = start_times.entry(number).or_insert(start);
+                *earliest = (*earliest).min(start);
+            }
+        }
+        start_times
+    }
+
+    // when SLURM expects the scheduled jobs to start, jobs without an
+    // estimate are missing from the map
+    pub fn estimated_start_times(
+        &self,
+    ) -> Result<HashMap<i32, DateTime<Local>>, SlurmInteractionError> {
+        if self.scheduled_jobs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let numbers: Vec<String> = self
+            .scheduled_jobs
+            .iter()
+            .map(|job| job.get_number().to_string())
+            .collect();
+        let args = [
+            format!("--jobs={}", numbers.join(",")),
+            String::from("--start"),
+            String::from("--noheader"),
+            String::from("--format"),
+            String::from("%i %S"),
+        ];
+        match self.run_slurm("squeue", &args, None) {
+            Ok(output) if output.status.success() => Ok(Self::parse_start_times(
+                &String::from_utf8_lossy(&output.stdout),
+            )),
+            Ok(output) => Err(SlurmInteractionError::BadSqueueResponse(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
+        }
+    }
+
+    // maps the compact squeue state (%t) of a job still in the queue, pending
+    // jobs held by a user or admin are told apart by their reason
+    fn live_status(state: &str, reason: &str) -> Option<SlurmJobStatus> {
+        match state {
+            "PD" if reason.starts_with("(JobHeld") => Some(HELD),
+            "PD" => Some(QUEUED),
+            "R" | "CF" | "CG" => Some(RUNNING),
+            _ => None,
+        }
+    }
+
+    // the %R column holds the reason for pending jobs and the node list for
+    // running ones, so it is only kept while the job is pending
+    fn pending_reason(state: &str, reason: &str) -> Option<String> {
+        match state {
+            "PD" => Some(
+                reason
+                    .trim_start_matches('(')
+                    .trim_end_matches(')')
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn update_live_status(&mut self, index: usize, row: &SqueueRow) {
+        self.scheduled_jobs[index].pending_reason = Self::pending_reason(&row.state, &row.reason);
+        match Self::live_status(&row.state, &row.reason) {
+            Some(status) if status != self.scheduled_jobs[index].status() => {
+                let mut job = self.scheduled_jobs.remove(index);
+                self.transition(&mut job, status);
+                self.scheduled_jobs.insert(index, job);
+            }
+            _ => {}
+        }
+    }
+
+    // `sacct -X` prints one row per allocation, i.e. one for a plain job and
+    // one per component (or array element) otherwise. The job is still active
+    // while any of them is, and ended as the first one that did not complete.
+    fn parse_sacct_output(out: &str) -> Option<(SlurmJobState, String)> {
+        let rows: Vec<(SlurmJobState, String)> = out
+            .lines()
+            .filter_map(|row| row.trim().split_once('|'))
+            .map(|(state, exit_code)| (SlurmJobState::parse(state), exit_code.to_string()))
+            .collect();
+        let active = rows.iter().position(|(state, _)| !state.is_terminal());
+        let failed = rows
+            .iter()
+            .position(|(state, _)| *state != SlurmJobState::Completed);
+        let index = active.or(failed).unwrap_or(0);
+        rows.into_iter().nth(index)
+    }
+
+    fn query_sacct(
+        &self,
+        number: i32,
+    ) -> Result<Option<(SlurmJobState, String)>, SlurmInteractionError> {
+        let args = [
+            String::from("-j"),
+            number.to_string(),
+            String::from("--format=State,ExitCode"),
+            String::from("-X"),
+            String::from("-n"),
+            String::from("-P"),
+        ];
+        match self.run_slurm("sacct", &args, None) {
+            Ok(output) if output.status.success() => Ok(Self::parse_sacct_output(
+                &String::from_utf8_lossy(&output.stdout),
+            )),
+            Ok(output) => Err(SlurmInteractionError::BadSacctResponse(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
+        }
+    }
+
+    // CPU time, elapsed time and peak memory of every finished job that was
+    // submitted, jobs sacct has no record of (yet) are reported without usage
+    pub fn resource_report(&self) -> Vec<JobUsage> {
+        self.finished_jobs
+            .iter()
+            .filter_map(|job| job.number.map(|number| (job, number)))
+            .map(|(job, number)| {
+                let args = [
+                    String::from("-j"),
+                    number.to_string(),
+                    String::from("--format=Elapsed,MaxRSS,TotalCPU"),
+                    String::from("-n"),
+                    String::from("-P"),
+                ];
+                let out = match self.run_slurm("sacct", &args, None) {
+                    Ok(output) if output.status.success() => {
+                        String::from_utf8_lossy(&output.stdout).to_string()
+                    }
+                    Ok(output) => {
+                        warn!(
+                            job_id = job.get_id().as_str(), job_number = number;
+                            "could not query the usage of job {}: {}", job,
+                            String::from_utf8_lossy(&output.stderr).trim()
+                        );
+                        String::new()
+                    }
+                    Err(why) => {
+                        warn!(
+                            job_id = job.get_id().as_str(), job_number = number;
+                            "could not query the usage of job {}: {}", job, why
+                        );
+                        String::new()
+                    }
+                };
+                JobUsage::parse_sacct(job.get_id(), number, &out)
+            })
+            .collect()
+    }
+
+    // determine how a job that left the queue ended, None if SLURM still
+    // considers it active (e.g. it was requeued) or sacct has to be asked
+    // again on the next poll
+    fn final_status(&self, job: &mut SlurmJob) -> Option<SlurmJobStatus> {
+        match self.query_sacct(job.get_number()) {
+            Ok(Some((state, exit_code))) => {
+                job.slurm_state = Some(state.clone());
+                job.exit_code = Some(exit_code.clone());
+                if state == SlurmJobState::Completed {
+                    Some(job.run_post_processing())
+                } else if state.is_terminal() {
+                    warn!(
+                        job_id = job.get_id().as_str(), job_number = job.number,
+                        slurm_state:% = state, exit_code = exit_code.as_str();
+                        "job {} crashed with state {} and exit code {}", job, state, exit_code
+                    );
+                    Some(CRASHED)
+                } else {
+                    None
+                }
+            }
+            Ok(None) if self.accounting_exhausted(job) => {
+                error!(
+                    job_id = job.get_id().as_str(), job_number = job.number;
+                    "no accounting record for job {} after {} polls, relying on post-processing",
+                    job, self.accounting_polls
+                );
+                Some(job.run_post_processing())
+            }
+            Ok(None) => {
+                warn!(
+                    job_id = job.get_id().as_str(), job_number = job.number;
+                    "no accounting record for job {} yet, asking again next poll", job
+                );
+                None
+            }
+            Err(why) => {
+                warn!(
+                    job_id = job.get_id().as_str(), job_number = job.number, error_kind = why.kind();
+                    "could not query sacct for job {}: {:?}, asking again next poll", job, why
+                );
+                None
+            }
+        }
+    }
+
+    // absent_polls keeps counting while the job waits for its accounting
+    // record, sacct is first asked once it reached the manager's absent_polls
+    fn accounting_exhausted(&self, job: &SlurmJob) -> bool {
+        job.absent_polls - self.absent_polls + 1 >= self.accounting_polls
+    }
+
+    fn check_on_jobs(&mut self) -> Result<i32, SlurmInteractionError> {
+        let running_jobs = self.get_running_jobs()?;
+        let mut finished_jobs = 0;
+        let mut index = 0;
+        while index < self.scheduled_jobs.len() {
+            if let Some(row) = running_jobs.get(&self.scheduled_jobs[index].get_number()) {
+                self.scheduled_jobs[index].absent_polls = 0;
+                self.update_live_status(index, row);
+                index += 1;
+                continue;
+            }
+            self.scheduled_jobs[index].absent_polls += 1;
+            if self.scheduled_jobs[index].absent_polls < self.absent_polls {
+                index += 1;
+                continue;
+            }
+            let mut job = self.scheduled_jobs.remove(index);
+            match self.final_status(&mut job) {
+                Some(status) => {
+                    self.transition(&mut job, status);
+                    self.finished_jobs.push(job);
+                    finished_jobs += 1;
+                }
+                None => {
+                    self.scheduled_jobs.insert(index, job);
+                    index += 1;
+                }
+            }
+        }
+        finished_jobs += self.cancel_timed_out_jobs();
+        self.skip_broken_dependents();
+        Result::Ok(finished_jobs)
+    }
+
+    fn cancel_timed_out_jobs(&mut self) -> i32 {
+        let Some(job_timeout) = self.job_timeout else {
+            return 0;
+        };
+        let Some(deadline) = TimeDelta::from_std(job_timeout)
+            .ok()
+            .and_then(|job_timeout| Local::now().checked_sub_signed(job_timeout))
+        else {
+            return 0;
+        };
+        let timed_out: Vec<i32> = self
+            .scheduled_jobs
+            .iter()
+            .filter(|job| {
+                job.submitted_at
+                    .is_some_and(|submitted| submitted < deadline)
+            })
+            .map(|job| job.get_number())
+            .collect();
+        let mut cancelled = 0;
+        for number in timed_out {
+            warn!(
+                job_number = number;
+                "job {} exceeded the job timeout of {:?}", number, job_timeout
+            );
+            match self.cancel_scheduled_job(number, TIMEOUT) {
+                Ok(()) => cancelled += 1,
+                Err(why) => error!(
+                    job_number = number, error_kind = why.kind();
+                    "could not cancel timed out job {}: {:?}", number, why
+                ),
+            }
+        }
+        cancelled
+    }
+
+    fn script_dir() -> PathBuf {
+        match std::env::var("TMP_DIR") {
+            Ok(tmp_dir) if !tmp_dir.is_empty() => PathBuf::from(tmp_dir),
+            _ => PathBuf::from("/tmp/"),
+        }
+    }
+
+    // the mode is also applied to a file left over from a previous attempt
+    #[cfg(unix)]
+    fn create_script_file(path: &Path, mode: u32) -> std::io::Result<File> {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)?;
+        file.set_permissions(std::fs::Permissions::from_mode(mode))?;
+        Ok(file)
+    }
+
+    #[cfg(not(unix))]
+    fn create_script_file(path: &Path, _mode: u32) -> std::io::Result<File> {
+        File::create(path)
+    }
+
+    // every job gets its own script file named after its id, the directory
+    // is created if it does not exist yet
+    fn write_slurm_script(
+        dir: &Path,
+        job: &SlurmJob,
+        script: &str,
+        mode: u32,
+    ) -> Result<PathBuf, SlurmInteractionError> {
+        let slurm_script = dir.join(format!("{}.slurm", job.get_id()));
+        let not_written = |why: std::io::Error| {
+            SlurmInteractionError::ScriptNotWritten(format!("{}: {}", slurm_script.display(), why))
+        };
+        std::fs::create_dir_all(dir).map_err(not_written)?;
+        let mut slurm_file = Self::create_script_file(&slurm_script, mode).map_err(not_written)?;
+        slurm_file
+            .write_all(script.as_bytes())
+            .map_err(not_written)?;
+        slurm_file.flush().map_err(not_written)?;
+        slurm_file.sync_all().map_err(not_written)?;
+        Ok(slurm_script)
+    }
+
+    fn schedule_job(&self, job: &mut SlurmJob) -> Result<i32, SlurmInteractionError> {
+        if let Some(ref log_dir) = self.log_dir {
+            job.assign_log_files(log_dir);
+        }
+        if job.has_posix_shell() {
+            job.apply_environment(&self.modules, &self.env);
+        } else if !(self.modules.is_empty() && self.env.is_empty()) {
+            warn!(
+                job_id = job.get_id().as_str();
+                "job {} does not run in a POSIX shell, the manager's modules and environment are not applied",
+                job
+            );
+        }
+        let mut script = job.generate_slurm_script();
+        if let Some(ref script_transform) = self.script_transform {
+            script = script_transform(script);
+        }
+        if self.keep_scripts
+            && let Err(why) =
+                Self::write_slurm_script(&Self::script_dir(), job, &script, self.script_mode)
+        {
+            warn!(
+                job_id = job.get_id().as_str(), error_kind = why.kind();
+                "could not keep the script of job {}: {:?}", job, why
+            );
+        }
+        match self.run_slurm("sbatch", &[], Some(&script)) {
+            Ok(output) => {
+                let job_id = Self::parse_sbatch_output(&output)?;
+                self.transition(job, SUBMITTED);
+                Ok(job_id)
+            }
+            Err(bad_status) => Err(SlurmInteractionError::SlurmUnresponsive(
+                bad_status.to_string(),
+            )),
+        }
+    }
+
+    // "Submitted batch job 42", followed by " on cluster <name>" when a
+    // cluster is selected
+    fn parse_sbatch_output(output: &Output) -> Result<i32, SlurmInteractionError> {
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let number = stdout
+            .split_whitespace()
+            .find_map(|word| word.parse::<i32>().ok());
+        match number {
+            Some(number) if output.status.success() => Ok(number),
+            _ => Err(SlurmInteractionError::BadSbatchResponse {
+                stdout,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                exit_code: output.status.code(),
+            }),
+        }
+    }
+
+    // Jobs with the highest priority are scheduled first, jobs of equal
+    // priority in the order they were added. Jobs that are still backing off
+    // after a failed submission or wait for their prerequisites to be
+    // submitted are skipped. Jobs in `in_flight` are being submitted and count
+    // against the resource budgets like scheduled ones.
+    fn next_open_job(&mut self, in_flight: &[SlurmJob]) -> Option<SlurmJob> {
+        let now = Local::now();
+        let (index, after_ok) = self
+            .open_jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| job.retry_after.is_none_or(|retry_after| retry_after <= now))
+            .filter_map(|(index, job)| {
+                self.pending_dependencies(job)
+                    .map(|after_ok| (index, after_ok))
+            })
+            .max_by_key(|(index, _)| (self.open_jobs[*index].priority, Reverse(*index)))?;
+        if !self.fits_resource_budget(&self.open_jobs[index], in_flight) {
+            return None;
+        }
+        let mut job = self.open_jobs.remove(index)?;
+        job.after_ok = after_ok;
+        Some(job)
+    }
+
+    // a job larger than the whole budget is still submitted once nothing
+    // else is scheduled, otherwise it would wait forever
+    fn fits_resource_budget(&self, job: &SlurmJob, in_flight: &[SlurmJob]) -> bool {
+        if self.scheduled_jobs.is_empty() && in_flight.is_empty() {
+            return true;
+        }
+        let cpus_fit = self.max_total_cpus.is_none_or(|max_total_cpus| {
+            let scheduled: usize = self
+                .scheduled_jobs
+                .iter()
+                .chain(in_flight)
+                .map(SlurmJob::total_cpus)
+                .sum();
+            scheduled.saturating_add(job.total_cpus()) <= max_total_cpus
+        });
+        let memory_fit = self
+            .max_total_memory
+            .as_ref()
+            .is_none_or(|max_total_memory| {
+                let default_megabytes = self.default_job_megabytes();
+                let scheduled: u64 = self
+                    .scheduled_jobs
+                    .iter()
+                    .chain(in_flight)
+                    .map(|scheduled| scheduled.total_memory_megabytes(default_megabytes))
+                    .sum();
+                scheduled.saturating_add(job.total_memory_megabytes(default_megabytes))
+                    <= max_total_memory.as_megabytes()
+            });
+        cpus_fit && memory_fit
+    }
+
+    // jobs depending on a job that did not finish successfully can never run,
+    // open ones are skipped and already submitted ones are cancelled
+    fn skip_broken_dependents(&mut self) {
+        loop {
+            let mut skipped = 0;
+            while let Some(index) = self
+                .open_jobs
+                .iter()
+                .position(|job| self.has_failed_dependency(job))
+            {
+                let mut job = self.open_jobs.remove(index).expect("index was just found");
+                warn!(
+                    job_id = job.get_id().as_str();
+                    "skipping job {} because a prerequisite did not finish successfully", job
+                );
+                self.transition(&mut job, SKIPPED);
+                self.finished_jobs.push(job);
+                skipped += 1;
+            }
+            let broken: Vec<i32> = self
+                .scheduled_jobs
+                .iter()
+                .filter(|job| self.has_failed_dependency(job))
+                .map(|job| job.get_number())
+                .collect();
+            for number in broken {
+                match self.cancel_scheduled_job(number, SKIPPED) {
+                    Ok(()) => skipped += 1,
+                    Err(why) => error!(
+                        job_number = number, error_kind = why.kind();
+                        "could not cancel broken dependent {}: {:?}", number, why
+                    ),
+                }
+            }
+            if skipped == 0 {
+                break;
+            }
+        }
+    }
+
+    // wait 5s, 10s, 20s, ... before the next submission attempt
+    fn retry_backoff(failed_submissions: u32) -> TimeDelta {
+        let exponent = failed_submissions.saturating_sub(1).min(10);
+        TimeDelta::seconds(5 * 2_i64.pow(exponent))
+    }
+
+    fn handle_failed_submission(&mut self, mut job: SlurmJob) {
+        job.failed_submissions += 1;
+        if job.failed_submissions > self.max_retries {
+            error!(
+                job_id = job.get_id().as_str(), failed_submissions = job.failed_submissions;
+                "giving up on job {} after {} failed submissions", job, job.failed_submissions
+            );
+            self.transition(&mut job, FAILED);
+            self.finished_jobs.push(job);
+        } else {
+            job.retry_after = Some(Local::now() + Self::retry_backoff(job.failed_submissions));
+            self.open_jobs.push_front(job);
+        }
+    }
+
+    // submits the jobs on up to submit_concurrency threads, the results are
+    // returned in the order of the jobs
+    fn schedule_jobs(
+        &self,
+        jobs: Vec<SlurmJob>,
+    ) -> Vec<(SlurmJob, Result<i32, SlurmInteractionError>)> {
+        if jobs.len() == 1 {
+            return jobs
+                .into_iter()
+                .map(|mut job| {
+                    let result = self.schedule_job(&mut job);
+                    (job, result)
+                })
+                .collect();
+        }
+        thread::scope(|scope| {
+            let submissions: Vec<_> = jobs
+                .into_iter()
+                .map(|mut job| {
+                    scope.spawn(move || {
+                        let result = self.schedule_job(&mut job);
+                        (job, result)
+                    })
+                })
+                .collect();
+            submissions
+                .into_iter()
+                .map(|submission| {
+                    submission
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        })
+    }
+
+    // how many jobs may still be submitted, limited by max_queue and
+    // max_total_submissions
+    fn queue_room(&self) -> usize {
+        let room = (self.max_queue - self.scheduled_jobs.len() as i32).max(0) as usize;
+        match self.max_total_submissions {
+            Some(max_total_submissions) => {
+                room.min((max_total_submissions - self.total_submissions) as usize)
+            }
+            None => room,
+        }
+    }
+
+    // takes up to size open jobs that are ready to be submitted
+    fn next_batch(&mut self, size: usize) -> Vec<SlurmJob> {
+        let mut batch = Vec::new();
+        while batch.len() < size {
+            match self.next_open_job(&batch) {
+                Some(job) => batch.push(job),
+                None => break,
+            }
+        }
+        batch
+    }
+
+    // schedules a submitted job or hands a rejected one to the retry logic
+    fn handle_submission(
+        &mut self,
+        mut job: SlurmJob,
+        result: Result<i32, SlurmInteractionError>,
+    ) -> Result<(), SlurmInteractionError> {
+        match result {
+            Ok(job_id) => {
+                job.set_number(job_id);
+                job.submitted_at = Some(Local::now());
+                self.scheduled_jobs.push(job);
+                self.total_submissions += 1;
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    job_id = job.get_id().as_str(), error_kind = e.kind();
+                    "encountered issue {:?}", e
+                );
+                self.handle_failed_submission(job);
+                Err(e)
+            }
+        }
+    }
+
+    fn fill_up_queue(&mut self) -> Result<i32, Vec<SlurmInteractionError>> {
+        if self.draining || self.submission_limit_reached() {
+            self.warn_submission_limit();
+            return Ok(0);
+        }
+        let mut errors = Vec::<SlurmInteractionError>::new();
+        let mut queue_delta = self.queue_room();
+        let mut added_jobs = 0;
+        while queue_delta > 0 {
+            let batch = self.next_batch(queue_delta.min(self.submit_concurrency));
+            if batch.is_empty() {
+                break;
+            }
+            queue_delta -= batch.len();
+            for (job, result) in self.schedule_jobs(batch) {
+                match self.handle_submission(job, result) {
+                    Ok(()) => added_jobs += 1,
+                    Err(e) => errors.push(e),
+                }
+            }
+        }
+        self.warn_submission_limit();
+        if errors.is_empty() {
+            Ok(added_jobs)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn scancel(&self, number: i32) -> Result<(), SlurmInteractionError> {
+        match self.run_slurm("scancel", &[number.to_string()], None) {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => Err(SlurmInteractionError::BadScancelResponse(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
+        }
+    }
+
+    // cancel a single scheduled job by its SLURM job number
+    pub fn cancel_job(&mut self, number: i32) -> Result<(), SlurmInteractionError> {
+        self.cancel_scheduled_job(number, CANCELLED)
+    }
+
+    fn cancel_scheduled_job(
+        &mut self,
+        number: i32,
+        status: SlurmJobStatus,
+    ) -> Result<(), SlurmInteractionError> {
+        let index = self
+            .scheduled_jobs
+            .iter()
+            .position(|job| job.number == Some(number))
+            .ok_or(SlurmInteractionError::UnknownJob(number))?;
+        self.scancel(number)?;
+        let mut cancelled = self.scheduled_jobs.remove(index);
+        self.transition(&mut cancelled, status);
+        self.finished_jobs.push(cancelled);
+        Ok(())
+    }
+
+    // runs scontrol for a scheduled job and returns what it printed, `{job}`
+    // in an argument is replaced with the job number, e.g.
+    // scontrol(&["update", "JobId={job}", "TimeLimit=2:00:00"], 42). Without
+    // a placeholder the number is appended, as in scontrol(&["top"], 42). The
+    // status of the job is updated on the next poll.
+    pub fn scontrol(
+        &self,
+        args: &[&str],
+        job_number: i32,
+    ) -> Result<String, SlurmInteractionError> {
+        if !self
+            .scheduled_jobs
+            .iter()
+            .any(|job| job.number == Some(job_number))
+        {
+            return Err(SlurmInteractionError::UnknownJob(job_number));
+        }
+        let mut scontrol_args: Vec<String> = args
+            .iter()
+            .map(|arg| arg.replace("{job}", &job_number.to_string()))
+            .collect();
+        if !args.iter().any(|arg| arg.contains("{job}")) {
+            scontrol_args.push(job_number.to_string());
+        }
+        match self.run_slurm("scontrol", &scontrol_args, None) {
+            Ok(output) if output.status.success() => {
+                Ok(String::from_utf8_lossy(&output.stdout).to_string())
+            }
+            Ok(output) => Err(SlurmInteractionError::BadScontrolResponse(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            )),
+            Err(bad) => Err(SlurmInteractionError::SlurmUnresponsive(bad.to_string())),
+        }
+    }
+
+    // moves the scheduled job with this number from `from` to `to` unless it
+    // already moved on
+    fn update_scheduled_status(
+        &mut self,
+        number: i32,
+        from: &[SlurmJobStatus],
+        to: SlurmJobStatus,
+    ) {
+        if let Some(index) = self
+            .scheduled_jobs
+            .iter()
+            .position(|job| job.number == Some(number) && from.contains(&job.status()))
+        {
+            let mut job = self.scheduled_jobs.remove(index);
+            self.transition(&mut job, to);
+            self.scheduled_jobs.insert(index, job);
+        }
+    }
+
+    // SLURM's own priority of a pending job (scontrol update), which usually
+    // needs operator rights. Users can lower it with SlurmJobBuilder::set_nice.
+    pub fn set_job_priority(
+        &self,
+        number: i32,
+        priority: u32,
+    ) -> Result<(), SlurmInteractionError> {
+        let priority = format!("Priority={}", priority);
+        self.scontrol(&["update", "JobId={job}", &priority], number)
+            .map(|_| ())
+    }
+
+    // makes a held job eligible to run (scontrol release)
+    pub fn release_job(&mut self, number: i32) -> Result<(), SlurmInteractionError> {
+        self.scontrol(&["release"], number)?;
+        self.update_scheduled_status(number, &[HELD], QUEUED);
+        Ok(())
+    }
+
+    // keeps a pending job from starting until release_job is called for it
+    // (scontrol hold), running jobs cannot be held
+    pub fn hold_job(&mut self, number: i32) -> Result<(), SlurmInteractionError> {
+        self.scontrol(&["hold"], number)?;
+        self.update_scheduled_status(number, &[SUBMITTED, QUEUED], HELD);
+        Ok(())
+    }
+
+    // puts a running job back into SLURM's queue under the same number
+    // (scontrol requeue), unlike requeue which resubmits a failed job
+    pub fn requeue_job(&mut self, number: i32) -> Result<(), SlurmInteractionError> {
+        self.scontrol(&["requeue"], number)?;
+        self.update_scheduled_status(number, &[RUNNING], QUEUED);
+        Ok(())
+    }
+
+    // stop submitting open jobs but keep tracking and post-processing the
+    // scheduled ones, e.g. for a graceful shutdown. Open jobs stay open.
+    pub fn drain(&mut self) {
+        info!(
+            open_jobs = self.open_jobs.len();
+            "draining, {} open jobs will not be submitted", self.open_jobs.len()
+        );
+        self.draining = true;
+    }
+
+    // true once drain was called and every scheduled job has finished
+    pub fn is_drained(&self) -> bool {
+        self.draining && self.scheduled_jobs.is_empty()
+    }
+
+    // nothing is scheduled and nothing will be submitted anymore
+    fn is_done(&self) -> bool {
+        self.scheduled_jobs.is_empty() && (!self.submits_open_jobs() || self.open_jobs.is_empty())
+    }
+
+    fn submits_open_jobs(&self) -> bool {
+        !self.draining && !self.submission_limit_reached()
+    }
+
+    // cancel every scheduled job and drop all open jobs, jobs that could not
+    // be cancelled stay scheduled so the call can be retried
+    pub fn cancel_all(&mut self) -> Result<(), Vec<SlurmInteractionError>> {
+        while let Some(mut open) = self.open_jobs.pop_front() {
+            self.transition(&mut open, CANCELLED);
+            self.finished_jobs.push(open);
+        }
+        let numbers: Vec<i32> = self
+            .scheduled_jobs
+            .iter()
+            .map(|job| job.get_number())
+            .collect();
+        let errors: Vec<SlurmInteractionError> = numbers
+            .into_iter()
+            .filter_map(|number| self.cancel_job(number).err())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // one check on the queue followed by submitting new jobs, for callers that
+    // drive the polling cadence themselves instead of using manage_jobs
+    pub fn tick(&mut self) -> TickResult {
+        let mut result = TickResult::default();
+        match self.check_on_jobs() {
+            Result::Ok(finished_jobs) => {
+                info!(
+                    finished_jobs = finished_jobs;
+                    "jobs finished since last check {}", finished_jobs
+                );
+                result.finished = finished_jobs;
+            }
+            Result::Err(why) => {
+                warn!(
+                    error_kind = why.kind();
+                    "Error while checking on jobs: {:?}", why
+                );
+                result.errors.push(why);
+            }
+        }
+        // jobs can be submitted even if others in the same round failed
+        let scheduled_before = self.scheduled_jobs.len();
+        match self.fill_up_queue() {
+            Result::Ok(added_jobs) => {
+                if added_jobs > 0 {
+                    info!(
+                        submitted_jobs = added_jobs;
+                        "we scheduled {} new jobs", added_jobs
+                    );
+                }
+            }
+            Result::Err(why) => {
+                for e in &why {
+                    error!(error_kind = e.kind(); "scheduling error: {:?}", e);
+                }
+                error!(
+                    errors = why.len();
+                    "while scheduling jobs we encountered {} errors", why.len()
+                );
+                result.errors.extend(why);
+            }
+        }
+        result.submitted = (self.scheduled_jobs.len() - scheduled_before) as i32;
+        result.remaining = self.scheduled_jobs.len();
+        result.held_back = self.held_back_jobs();
+        if self.submits_open_jobs() {
+            result.remaining += self.open_jobs.len();
+        } else if self.is_drained() && result.finished > 0 {
+            info!(
+                open_jobs = self.open_jobs.len();
+                "draining complete, {} open jobs left", self.open_jobs.len()
+            );
+        }
+        result
+    }
+
+    fn end_time(for_sec: Option<i64>) -> DateTime<Local> {
+        let max_time_delta = 365 * 24 * 60; // one year worth of seconds
+        Local::now() + TimeDelta::seconds(for_sec.unwrap_or(max_time_delta))
+    }
+
+    // run loop until either the time is up or there is nothing left to do
+    fn keep_managing(&self, end_time: DateTime<Local>) -> bool {
+        Local::now() < end_time && !self.is_done()
+    }
+
+    fn log_remaining(result: &TickResult, end_time: DateTime<Local>) {
+        let time_remaining = end_time - Local::now();
+        info!(
+            remaining_jobs = result.remaining;
+            "there are {} jobs remaining to be completed within the next {} seconds",
+            result.remaining,
+            time_remaining.as_seconds_f32()
+        );
+    }
+
+    // Ok(true) once every job is done, Ok(false) if the time ran out first.
+    // Errors of the ticks do not stop managing, they are all returned once
+    // managing stops, see SlurmInteractionError::is_transient. After drain or
+    // once max_total_submissions is reached, jobs that were still open do not
+    // count as remaining.
+    pub fn manage_jobs(
+        &mut self,
+        for_sec: Option<i64>,
+    ) -> Result<bool, Vec<SlurmInteractionError>> {
+        let end_time = Self::end_time(for_sec);
+        let mut errors = Vec::new();
+        while self.keep_managing(end_time) {
+            let mut result = self.tick();
+            errors.append(&mut result.errors);
+            if !result.work_remaining() {
+                break;
+            }
+            Self::log_remaining(&result, end_time);
+            thread::sleep(self.poll_interval);
+        }
+        Self::managed(self.is_done(), errors)
+    }
+
+    fn managed(
+        done: bool,
+        errors: Vec<SlurmInteractionError>,
+    ) -> Result<bool, Vec<SlurmInteractionError>> {
+        if errors.is_empty() {
+            Ok(done)
+        } else {
+            Err(errors)
+        }
+    }
+
+    // same as manage_jobs but waits between two ticks without blocking a
+    // runtime thread, on any tokio runtime. The SLURM commands of a tick run
+    // synchronously on tokio's blocking pool, so the manager is moved there
+    // and handed back together with the result once managing stops.
+    #[cfg(feature = "tokio")]
+    pub async fn manage_jobs_async(
+        self,
+        for_sec: Option<i64>,
+    ) -> (SlurmManager, Result<bool, Vec<SlurmInteractionError>>) {
+        let end_time = Self::end_time(for_sec);
+        let mut manager = self;
+        let mut errors = Vec::new();
+        while manager.keep_managing(end_time) {
+            let (ticked, mut result) = tokio::task::spawn_blocking(move || {
+                let result = manager.tick();
+                (manager, result)
+            })
+            .await
+            .unwrap_or_else(|why| std::panic::resume_unwind(why.into_panic()));
+            manager = ticked;
+            errors.append(&mut result.errors);
+            if !result.work_remaining() {
+                break;
+            }
+            Self::log_remaining(&result, end_time);
+            tokio::time::sleep(manager.poll_interval).await;
+        }
+        let done = manager.is_done();
+        (manager, Self::managed(done, errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //use crate::logging::Logger;
+    use super::*;
+    use crate::het_component::HetComponent;
+    use crate::job_builder::SlurmJobBuilder;
+    use crate::job_post_processing::SlurmJobPostProcessing;
+    use crate::memory_size::Memory::{GigaByte, MegaByte};
+    use crate::mock_scheduler::MockScheduler;
+    use serial_test::serial;
+
+    fn init_logger() {
+        //todo: do we need to init anything here?
+    }
+
+    fn sleep_job(wdir: Option<String>) -> SlurmJob {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_description(String::from("sleeps for 5 seconds"));
+        match wdir {
+            Some(dir) => job.set_working_directory(dir).build(),
+            None => job.build(),
+        }
+    }
+
+    // Unique path for a file a job can `touch` as a side-effect marker of
+    // having actually completed its command (as opposed to being killed
+    // by SLURM for exceeding a time or memory limit).
+    fn marker_path() -> String {
+        SlurmManager::script_dir()
+            .join(format!("marker_{}", uuid::Uuid::new_v4()))
+            .display()
+            .to_string()
+    }
+
+    fn marker_exists(path: &str) -> bool {
+        std::path::Path::new(path).exists()
+    }
+
+    // Post-processing that succeeds only if the job actually ran its
+    // command to completion and left the marker file behind.
+    fn marker_post_processing(marker: &str) -> SlurmJobPostProcessing {
+        SlurmJobPostProcessing::new(&[("marker".to_string(), marker.to_string())], |params| {
+            std::path::Path::new(&params["marker"]).exists()
+        })
+    }
+
+    #[test]
+    fn generate_job_command() {
+        let job = sleep_job(None);
+        assert_eq!(job.generate_slurm_commands(), "sleep 5\n");
+    }
+
+    #[test]
+    fn generate_job_command_wdir() {
+        let job = sleep_job(Some("/tmp/".parse().unwrap()));
+        assert_eq!(
+            job.generate_slurm_commands(),
+            r"pushd /tmp/
+sleep 5
+popd
+"
+        );
+    }
+
+    #[test]
+    fn generate_full_script() {
+        let job = sleep_job(None);
+        let mut expected: String = String::from("#!/bin/bash\n");
+        expected += format!("#SBATCH --job-name={}\n", job.get_id()).as_str();
+        expected += "#SBATCH --output=/dev/null\n";
+        expected += "#SBATCH --error=/dev/null\n";
+        expected += "#SBATCH --cpus-per-task=1\n";
+        expected += "\n\n";
+        expected += "echo START: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        expected += "sleep 5\n";
+        expected += "\necho END: `date +%Y-%m-%dT%H:%M:%S%z`\n";
+        assert_eq!(job.generate_slurm_script(), expected);
+    }
+
+    #[test]
+    fn generate_script_without_memory_request() {
+        let script = sleep_job(None).generate_slurm_script();
+        assert!(!script.contains("--mem"));
+    }
+
+    #[test]
+    fn generate_script_with_memory_request() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_memory(GigaByte(4))
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --mem=4G\n"));
+    }
+
+    #[test]
+    fn jobs_without_memory_request_fit_any_memory_budget() {
+        let mut manager = SlurmManager::new(3);
+        manager.set_max_total_memory(MegaByte(1));
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        let first = manager.next_open_job(&[]).expect("first job");
+        assert_eq!(first.total_memory_megabytes(0), 0);
+        manager.scheduled_jobs.push(first);
+        assert!(manager.next_open_job(&[]).is_some());
+    }
+
+    #[test]
+    fn default_job_memory_counts_against_the_memory_budget() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_max_total_memory(GigaByte(4));
+        manager.set_default_job_memory(GigaByte(2));
+        manager.add_jobs((0..3).map(|_| sleep_job(None)).collect());
+        assert_eq!(manager.tick().submitted, 2);
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn default_job_memory_does_not_replace_requested_memory() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nodes(2)
+            .set_memory(MegaByte(100))
+            .add_het_component(HetComponent::new())
+            .build();
+        assert!(!job.requests_memory());
+        assert_eq!(job.total_memory_megabytes(1000), 1200);
+        assert!(job_with(1, MegaByte(100)).requests_memory());
+    }
+
+    #[test]
+    fn generate_script_exports_env() {
+        let job = SlurmJobBuilder::new(String::from("echo $GREETING"))
+            .add_env("GREETING".to_string(), "hello world".to_string())
+            .add_env("DEPTH".to_string(), "3".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        let exports = "export DEPTH='3'\nexport GREETING='hello world'\n";
+        assert!(script.contains(exports));
+        assert!(
+            script.find(exports).unwrap() < script.find("echo $GREETING").unwrap(),
+            "variables must be exported before the command runs"
+        );
+    }
+
+    #[test]
+    fn generate_script_escapes_env_quotes() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_env("QUOTED".to_string(), "it's \"here\"".to_string())
+            .build();
+        assert_eq!(
+            job.generate_env_exports(),
+            "export QUOTED='it'\\''s \"here\"'\n"
+        );
+    }
+
+    #[test]
+    fn generate_script_without_env_has_no_exports() {
+        assert!(!sleep_job(None).generate_slurm_script().contains("export"));
+    }
+
+    #[test]
+    fn generate_script_loads_modules_in_order() {
+        let job = SlurmJobBuilder::new(String::from("python3 run.py"))
+            .add_module("gcc/12".to_string())
+            .add_module("python/3.11".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        let loads = "module load gcc/12\nmodule load python/3.11\n";
+        assert!(script.contains(loads));
+        assert!(script.find(loads).unwrap() < script.find("python3 run.py").unwrap());
+    }
+
+    #[test]
+    fn set_modules_replaces_added_modules() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_module("gcc/11".to_string())
+            .set_modules(vec!["gcc/12".to_string()])
+            .build();
+        assert_eq!(job.generate_module_loads(), "module load gcc/12\n");
+    }
+
+    #[test]
+    fn generate_script_without_modules() {
+        assert!(
+            !sleep_job(None)
+                .generate_slurm_script()
+                .contains("module load")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_array() {
+        let job = SlurmJobBuilder::new(String::from("echo $SLURM_ARRAY_TASK_ID"))
+            .set_array("0-15%4".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --array=0-15%4\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_sequential_array() {
+        let job = SlurmJobBuilder::new(String::from("./step $SLURM_ARRAY_TASK_ID"))
+            .set_array("0-9".to_string())
+            .set_array_sequential(true)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --array=0-9%1\n")
+        );
+    }
+
+    #[test]
+    fn sequential_array_replaces_limit() {
+        let job = SlurmJobBuilder::new(String::from("./step $SLURM_ARRAY_TASK_ID"))
+            .set_array_sequential(true)
+            .set_array("1-10:2%4".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --array=1-10:2%1\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_array() {
+        assert!(!sleep_job(None).generate_slurm_script().contains("--array"));
+    }
+
+    #[test]
+    fn generate_full_script_with_all_options() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_file("out.log".to_string())
+            .set_error_file("err.log".to_string())
+            .set_cpus(4)
+            .set_memory(GigaByte(8))
+            .set_max_run_time("1-02:30:00".to_string())
+            .set_working_directory("/tmp/".to_string())
+            .set_reservation("maintenance".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --output=out.log\n"));
+        assert!(script.contains("#SBATCH --error=err.log\n"));
+        assert!(script.contains("#SBATCH --cpus-per-task=4\n"));
+        assert!(script.contains("#SBATCH --mem=8G\n"));
+        assert!(script.contains("#SBATCH --time=1-02:30:00\n"));
+        assert!(script.contains("#SBATCH --reservation=maintenance\n"));
+        assert!(script.contains("pushd /tmp/\n"));
+        assert!(script.contains("popd\n"));
+    }
+
+    #[test]
+    fn generate_script_with_kilobyte_memory() {
+        use crate::memory_size::Memory::KiloByte;
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_memory(KiloByte(512))
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --mem=512K\n"));
+    }
+
+    #[test]
+    fn generate_script_with_terabyte_memory() {
+        use crate::memory_size::Memory::TeraByte;
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_memory(TeraByte(2))
+            .build();
+        assert!(job.generate_slurm_script().contains("#SBATCH --mem=2T\n"));
+    }
+
+    #[test]
+    fn generate_script_with_memory_per_cpu() {
+        use crate::memory_size::Memory::MegaByte;
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(4)
+            .set_memory_per_cpu(MegaByte(512))
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --mem-per-cpu=512M\n"));
+        assert!(!script.contains("#SBATCH --mem="));
+    }
+
+    #[test]
+    fn generate_script_with_preamble_and_epilogue() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_module("gcc/12".to_string())
+            .set_preamble("set -euo pipefail\ntrap 'echo interrupted' TERM".to_string())
+            .set_epilogue("rm -rf \"$TMPDIR/scratch\"\n".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        let preamble = script
+            .find("\n\nset -euo pipefail\ntrap 'echo interrupted' TERM\n")
+            .expect("preamble after the #SBATCH block");
+        assert!(preamble < script.find("module load gcc/12").unwrap());
+        assert!(
+            script.ends_with("echo END: `date +%Y-%m-%dT%H:%M:%S%z`\nrm -rf \"$TMPDIR/scratch\"\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_preamble_for_python() {
+        let job = SlurmJobBuilder::new(String::from("main()"))
+            .set_shell("/usr/bin/env python3".to_string())
+            .set_preamble("from pipeline import main".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .ends_with("\nfrom pipeline import main\nmain()\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_preamble_keeps_markers_around_command() {
+        let script = sleep_job(None).generate_slurm_script();
+        assert!(script.contains("\n\necho START"));
+        assert!(script.ends_with("echo END: `date +%Y-%m-%dT%H:%M:%S%z`\n"));
+    }
+
+    #[test]
+    fn generate_script_with_timestamp_markers_by_default() {
+        let script = sleep_job(None).generate_slurm_script();
+        assert!(script.contains("echo START: `date +%Y-%m-%dT%H:%M:%S%z`\n"));
+        assert!(script.contains("echo END: `date +%Y-%m-%dT%H:%M:%S%z`\n"));
+    }
+
+    #[test]
+    fn generate_script_with_timestamp_markers_enabled() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_timestamp_markers(true)
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .ends_with("echo START: `date +%Y-%m-%dT%H:%M:%S%z`\nsleep 5\n\necho END: `date +%Y-%m-%dT%H:%M:%S%z`\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_without_timestamp_markers() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_timestamp_markers(false)
+            .set_epilogue("echo cleanup".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(!script.contains("echo START") && !script.contains("echo END"));
+        assert!(script.ends_with("\n\n\nsleep 5\n\necho cleanup\n"));
+    }
+
+    #[test]
+    fn manager_environment_skips_jobs_without_posix_shell() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_module("python/3.12".to_string());
+        let job = SlurmJobBuilder::new(String::from("print(1)"))
+            .set_shell("/usr/bin/env python3".to_string())
+            .build();
+        manager.submit_now(&job).expect("submitted");
+        assert!(!mock.submitted_scripts()[0].contains("module load"));
+    }
+
+    #[test]
+    fn generate_script_keeps_filename_patterns() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_array("0-3".to_string())
+            .set_output_pattern("logs/%x_%A_%a.out".to_string())
+            .set_error_pattern("logs/%x_%A_%4a.err".to_string())
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --output=logs/%x_%A_%a.out\n"));
+        assert!(script.contains("#SBATCH --error=logs/%x_%A_%4a.err\n"));
+    }
+
+    #[test]
+    fn generate_script_keeps_percent_in_plain_file_names() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_file("run-100%%-%j.log".to_string())
+            .build();
+        assert!(
+            job.generate_slurm_script()
+                .contains("#SBATCH --output=run-100%%-%j.log\n")
+        );
+    }
+
+    #[test]
+    fn generate_script_with_het_components() {
+        let job = SlurmJobBuilder::new(String::from(
+            "srun --het-group=0 prep : --het-group=1 train",
+        ))
+        .set_ntasks(4)
+        .add_raw_directive("--switches=1".to_string())
+        .add_het_component(
+            HetComponent::new()
+                .set_partition("gpu".to_string())
+                .set_gres("gpu:1".to_string()),
+        )
+        .add_het_component(HetComponent::new().set_cpus(8))
+        .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains(
+            "#SBATCH --switches=1\n#SBATCH hetjob\n#SBATCH --partition=gpu\n#SBATCH --cpus-per-task=1\n\
+             #SBATCH --gres=gpu:1\n#SBATCH hetjob\n#SBATCH --cpus-per-task=8\n\n"
+        ));
+        assert_eq!(script.matches("hetjob").count(), 2);
+    }
+
+    #[test]
+    fn het_components_count_towards_resource_totals() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(2)
+            .set_memory(GigaByte(1))
+            .add_het_component(HetComponent::new().set_cpus(4).set_memory(GigaByte(2)))
+            .build();
+        assert_eq!(job.total_cpus(), 6);
+        assert_eq!(job.total_memory_megabytes(0), 3072);
+    }
+
+    #[test]
+    fn generate_script_without_het_components() {
+        assert!(!sleep_job(None).generate_slurm_script().contains("hetjob"));
+    }
+
+    #[test]
+    fn to_script_string_matches_generated_script() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_partition("gpu".to_string())
+            .build();
+        assert_eq!(job.to_script_string(), job.generate_slurm_script());
+        assert!(job.to_script_string().contains("#SBATCH --partition=gpu\n"));
+    }
+
+    #[test]
+    fn generate_script_with_chdir() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_working_directory("/scratch/run1".to_string())
+            .set_chdir(true)
+            .build();
+        let script = job.generate_slurm_script();
+        assert!(script.contains("#SBATCH --chdir=/scratch/run1\n"));
+        assert!(!script.contains("pushd"));
+        assert!(!script.contains("popd"));
+        assert_eq!(job.generate_slurm_commands(), "sleep 5\n");
+    }
+
+    #[test]
+    fn generate_script_with_chdir_but_no_working_directory() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_chdir(true)
+            .build();
+        assert!(!job.generate_slurm_script().contains("--chdir"));
+    }
+
+    #[test]
+    fn generate_script_with_pushd_by_default() {
+        let script = sleep_job(Some("/tmp/".to_string())).generate_slurm_script();
+        assert!(script.contains("pushd /tmp/\n"));
+        assert!(!script.contains("--chdir"));
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn create_and_run_jobs() {
+        let job = sleep_job(None);
+        init_logger();
+        let mut manager = SlurmManager::new(2);
+        manager.add_job(&job);
+        let pre_start = manager.check_on_jobs().expect("Should have checked no job");
+        let scheduled = manager.fill_up_queue().expect("Couldn't fill up queue");
+        let running = manager.get_running_jobs().expect("get running jobs").len();
+        let done = manager.manage_jobs(Some(20)).expect("no SLURM errors");
+        assert_eq!(pre_start, 0);
+        assert_eq!(scheduled, 1);
+        assert_eq!(running, 1);
+        assert!(done);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn create_and_run_multiple_jobs() {
+        let job_one = sleep_job(None);
+        let job_two = sleep_job(None);
+        init_logger();
+        let mut manager = SlurmManager::new(2);
+        manager.add_jobs(Vec::from([job_one, job_two]));
+        let pre_start = manager.check_on_jobs().expect("Should have checked no job");
+        let scheduled = manager.fill_up_queue().expect("Couldn't fill up queue");
+        let running = manager.get_running_jobs().expect("get running jobs").len();
+        let done = manager.manage_jobs(Some(20)).expect("no SLURM errors");
+        assert_eq!(pre_start, 0);
+        assert_eq!(scheduled, 2);
+        assert_eq!(running, 2);
+        assert!(done);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn manage_jobs_returns_false_when_time_runs_out() {
+        // sleep 30 won't finish within the 5-second budget
+        let job = SlurmJobBuilder::new(String::from("sleep 30")).build();
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&job);
+        let all_done = manager.manage_jobs(Some(5)).expect("no SLURM errors");
+        assert!(
+            !all_done,
+            "manage_jobs should return false when the time limit expires before all jobs finish"
+        );
+        assert!(
+            !(manager.open_jobs.is_empty() && manager.scheduled_jobs.is_empty()),
+            "the unfinished job should still be tracked (open or scheduled), not silently dropped"
+        );
+        // the job is intentionally left running by this test; cancel it so it
+        // doesn't linger in squeue and pollute subsequent tests
+        for job in &manager.scheduled_jobs {
+            let _ = std::process::Command::new("scancel")
+                .arg(job.get_number().to_string())
+                .output();
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn slurm_time_limit_kills_job() {
+        // the job would take 30s but is only allowed 5s by SLURM's --time
+        let marker = marker_path();
+        let _ = std::fs::remove_file(&marker);
+        let job = SlurmJobBuilder::new(format!("sleep 30 && touch {}", marker))
+            .set_max_run_time("0-00:00:05".to_string())
+            .set_on_finished(marker_post_processing(&marker))
+            .build();
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&job);
+        manager.manage_jobs(Some(30)).expect("no SLURM errors");
+        assert!(
+            !marker_exists(&marker),
+            "job killed by the SLURM time limit should never reach the `touch` command"
+        );
+        assert_eq!(
+            manager.successful_jobs(),
+            0,
+            "a job killed by the SLURM time limit must not be counted as successful"
+        );
+        let _ = std::fs::remove_file(&marker);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster with memory-limit enforcement (cgroups) enabled (run with --include-ignored)"]
+    fn memory_limit_kills_job() {
+        // allocate 200MB of tmpfs-backed memory against a 50MB SLURM cap
+        let marker = marker_path();
+        let bigfile = format!("/dev/shm/slurm_test_bigfile_{}", uuid::Uuid::new_v4());
+        let _ = std::fs::remove_file(&marker);
+        let _ = std::fs::remove_file(&bigfile);
+        let command = format!(
+            "dd if=/dev/zero of={} bs=1M count=200 && touch {} && rm -f {}",
+            bigfile, marker, bigfile
+        );
+        let job = SlurmJobBuilder::new(command)
+            .set_memory(crate::memory_size::Memory::MegaByte(50))
+            .set_on_finished(marker_post_processing(&marker))
+            .build();
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&job);
+        manager.manage_jobs(Some(60)).expect("no SLURM errors");
+        assert!(
+            !marker_exists(&marker),
+            "job exceeding its memory limit should be OOM-killed before writing the marker"
+        );
+        assert_eq!(
+            manager.successful_jobs(),
+            0,
+            "an OOM-killed job must not be counted as successful"
+        );
+        let _ = std::fs::remove_file(&marker);
+        let _ = std::fs::remove_file(&bigfile);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn queue_cap_never_exceeded() {
+        let max_queue = 2;
+        let mut manager = SlurmManager::new(max_queue);
+        for _ in 0..6 {
+            manager.add_job(&sleep_job(None));
+        }
+        let end_time = Local::now() + TimeDelta::seconds(60);
+        loop {
+            manager.check_on_jobs().expect("check on jobs");
+            manager.fill_up_queue().expect("fill up queue");
+            let running = manager.get_running_jobs().expect("get running jobs").len() as i32;
+            assert!(
+                running <= max_queue,
+                "queue cap of {} exceeded: {} jobs running",
+                max_queue,
+                running
+            );
+            if manager.open_jobs.is_empty() && manager.scheduled_jobs.is_empty() {
+                break;
+            }
+            assert!(
+                Local::now() < end_time,
+                "jobs did not complete within the test budget"
+            );
+            thread::sleep(Duration::from_secs(2));
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn crashed_job_not_counted_as_successful() {
+        let always_fail = SlurmJobPostProcessing::new(&[], |_| false);
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_on_finished(always_fail)
+            .build();
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&job);
+        manager.manage_jobs(Some(15)).expect("no SLURM errors");
+        assert_eq!(
+            manager.successful_jobs(),
+            0,
+            "a job whose post-processing fails should not be counted as successful"
+        );
+    }
+
+    #[test]
+    fn open_jobs_are_scheduled_in_insertion_order() {
+        let mut manager = SlurmManager::new(3);
+        for command in ["echo first", "echo second", "echo third"] {
+            manager.add_job(&SlurmJobBuilder::new(command.to_string()).build());
+        }
+        let order: Vec<String> = std::iter::from_fn(|| manager.next_open_job(&[]))
+            .map(|job| job.command)
+            .collect();
+        assert_eq!(order, vec!["echo first", "echo second", "echo third"]);
+    }
+
+    fn job_with_priority(command: &str, priority: i32) -> SlurmJob {
+        SlurmJobBuilder::new(command.to_string())
+            .set_priority(priority)
+            .build()
+    }
+
+    #[test]
+    fn open_jobs_with_higher_priority_jump_ahead() {
+        let mut manager = SlurmManager::new(3);
+        manager.add_job(&job_with_priority("echo backlog", 0));
+        manager.add_job(&job_with_priority("echo urgent", 10));
+        manager.add_job(&job_with_priority("echo background", -5));
+        manager.add_job(&job_with_priority("echo important", 5));
+        let order: Vec<String> = std::iter::from_fn(|| manager.next_open_job(&[]))
+            .map(|job| job.command)
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                "echo urgent",
+                "echo important",
+                "echo backlog",
+                "echo background"
+            ]
+        );
+    }
+
+    #[test]
+    fn open_jobs_with_equal_priority_keep_insertion_order() {
+        let mut manager = SlurmManager::new(3);
+        manager.add_job(&job_with_priority("echo first", 1));
+        manager.add_job(&job_with_priority("echo second", 1));
+        assert_eq!(manager.next_open_job(&[]).unwrap().command, "echo first");
+    }
+
+    #[test]
+    fn backing_off_job_does_not_block_lower_priority_jobs() {
+        let mut manager = SlurmManager::new(3);
+        manager.add_job(&job_with_priority("echo urgent", 10));
+        manager.add_job(&job_with_priority("echo normal", 0));
+        manager.open_jobs[0].retry_after = Some(Local::now() + TimeDelta::seconds(60));
+        assert_eq!(manager.next_open_job(&[]).unwrap().command, "echo normal");
+        assert!(manager.next_open_job(&[]).is_none());
+    }
+
+    #[test]
+    fn set_number_twice_keeps_the_latest_number() {
+        let mut job = sleep_job(None);
+        job.set_number(10);
+        job.set_number(11);
+        assert_eq!(job.get_number(), 11);
+    }
+
+    #[test]
+    fn set_number_with_the_same_number_is_harmless() {
+        let mut job = sleep_job(None);
+        job.set_number(10);
+        job.set_number(10);
+        assert_eq!(job.number, Some(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "no number set for the job")]
+    fn get_number_without_number_panics() {
+        sleep_job(None).get_number();
+    }
+
+    #[test]
+    fn job_missing_once_is_kept_with_absent_polls() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.set_absent_polls(2);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        mock.finish(1, "COMPLETED", "0:0");
+        assert_eq!(manager.check_on_jobs().unwrap(), 0);
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+        assert_eq!(manager.check_on_jobs().unwrap(), 1);
+        assert_eq!(manager.finished_jobs[0].status(), FINISHED);
+    }
+
+    #[test]
+    fn job_reappearing_resets_absent_polls() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_absent_polls(2);
+        let squeue = "if [ -e \"$(dirname \"$0\")/listed\" ]; then echo '4 main job user R 0:01 1 node01'; fi";
+        let dir = fake_slurm_dir(&[("squeue", squeue), ("sacct", "exit 0")]);
+        manager.set_binary_path(dir.clone());
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        manager.check_on_jobs().unwrap();
+        std::fs::write(dir.join("listed"), "").unwrap();
+        manager.check_on_jobs().unwrap();
+        assert_eq!(manager.scheduled_jobs[0].absent_polls, 0);
+        std::fs::remove_file(dir.join("listed")).unwrap();
+        assert_eq!(manager.check_on_jobs().unwrap(), 0);
+        assert_eq!(manager.scheduled_jobs[0].status(), RUNNING);
+    }
+
+    #[test]
+    fn failing_sacct_keeps_job_scheduled() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[
+            ("squeue", "exit 0"),
+            ("sacct", "echo 'slurmdbd: connection refused' >&2; exit 1"),
+        ]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        for _ in 0..5 {
+            assert_eq!(manager.check_on_jobs().unwrap(), 0);
+        }
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+        assert!(manager.finished_jobs.is_empty());
+    }
+
+    #[test]
+    fn missing_accounting_record_falls_back_after_accounting_polls() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_accounting_polls(2);
+        manager.set_binary_path(fake_slurm_dir(&[("squeue", "exit 0"), ("sacct", "exit 0")]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        assert_eq!(manager.check_on_jobs().unwrap(), 0);
+        assert_eq!(manager.check_on_jobs().unwrap(), 1);
+        assert_eq!(manager.finished_jobs[0].status(), FINISHED);
+    }
+
+    #[test]
+    fn accounting_polls_count_from_absent_polls() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_absent_polls(2);
+        manager.set_accounting_polls(1);
+        manager.set_binary_path(fake_slurm_dir(&[("squeue", "exit 0"), ("sacct", "exit 0")]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        assert_eq!(manager.check_on_jobs().unwrap(), 0);
+        assert_eq!(manager.check_on_jobs().unwrap(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "accounting_polls must be at least 1")]
+    fn set_accounting_polls_rejects_zero() {
+        SlurmManager::new(1).set_accounting_polls(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "absent_polls must be at least 1")]
+    fn set_absent_polls_rejects_zero() {
+        SlurmManager::new(1).set_absent_polls(0);
+    }
+
+    #[test]
+    fn jobs_lists_every_bucket() {
+        let (mut manager, mock) = mock_manager(2);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None), sleep_job(None)]);
+        manager.tick();
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.check_on_jobs().unwrap();
+        let buckets: Vec<(Option<i32>, JobBucket)> = manager
+            .jobs()
+            .map(|(job, bucket)| (job.number, bucket))
+            .collect();
+        assert_eq!(
+            buckets,
+            [
+                (None, JobBucket::Open),
+                (Some(2), JobBucket::Scheduled),
+                (Some(1), JobBucket::Finished)
+            ]
+        );
+    }
+
+    #[test]
+    fn jobs_lists_open_jobs_in_order() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&SlurmJobBuilder::new("echo first".to_string()).build());
+        manager.add_job(&SlurmJobBuilder::new("echo second".to_string()).build());
+        let commands: Vec<&str> = manager.jobs().map(|(job, _)| job.command()).collect();
+        assert_eq!(commands, ["echo first", "echo second"]);
+    }
+
+    #[test]
+    fn jobs_of_empty_manager() {
+        assert_eq!(SlurmManager::new(1).jobs().count(), 0);
+    }
+
+    #[test]
+    fn add_jobs_preserves_batch_order() {
+        let mut manager = SlurmManager::new(3);
+        manager.add_job(&SlurmJobBuilder::new("echo single".to_string()).build());
+        manager.add_jobs(Vec::from([
+            SlurmJobBuilder::new("echo batch one".to_string()).build(),
+            SlurmJobBuilder::new("echo batch two".to_string()).build(),
+        ]));
+        let first = manager.next_open_job(&[]).expect("first job");
+        let second = manager.next_open_job(&[]).expect("second job");
+        assert_eq!(first.command, "echo single");
+        assert_eq!(second.command, "echo batch one");
+    }
+
+    fn job_with(cpus: usize, memory: Memory) -> SlurmJob {
+        SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(cpus)
+            .set_memory(memory)
+            .build()
+    }
+
+    #[test]
+    fn cpu_budget_stops_filling_the_queue() {
+        let (mut manager, mock) = mock_manager(10);
+        manager.set_max_total_cpus(8);
+        manager.add_jobs(vec![
+            job_with(4, MegaByte(100)),
+            job_with(4, MegaByte(100)),
+            job_with(1, MegaByte(100)),
+        ]);
+        assert_eq!(manager.tick().submitted, 2);
+        mock.finish(1, "COMPLETED", "0:0");
+        assert_eq!(manager.tick().submitted, 1);
+    }
+
+    #[test]
+    fn memory_budget_keeps_submission_order() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_max_total_memory(GigaByte(8));
+        manager.add_jobs(vec![
+            job_with(1, GigaByte(6)),
+            job_with(1, GigaByte(4)),
+            job_with(1, GigaByte(1)),
+        ]);
+        assert_eq!(manager.tick().submitted, 1);
+        assert_eq!(manager.open_jobs.len(), 2);
+    }
+
+    #[test]
+    fn oversized_job_runs_alone() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_max_total_cpus(2);
+        manager.add_jobs(vec![
+            job_with(16, MegaByte(100)),
+            job_with(1, MegaByte(100)),
+        ]);
+        assert_eq!(manager.tick().submitted, 1);
+        assert_eq!(manager.scheduled_jobs[0].total_cpus(), 16);
+    }
+
+    #[test]
+    fn total_resources_account_for_tasks_and_nodes() {
+        let per_cpu = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(2)
+            .set_ntasks(4)
+            .set_memory_per_cpu(MegaByte(500))
+            .build();
+        assert_eq!(per_cpu.total_cpus(), 8);
+        assert_eq!(per_cpu.total_memory_megabytes(0), 4000);
+        let per_node = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nodes(3)
+            .set_memory(GigaByte(2))
+            .build();
+        assert_eq!(per_node.total_memory_megabytes(0), 6144);
+    }
+
+    #[test]
+    fn total_resources_account_for_ntasks_per_node() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(2)
+            .set_nodes(3)
+            .set_ntasks_per_node(4)
+            .set_memory_per_cpu(MegaByte(100))
+            .build();
+        assert_eq!(job.total_cpus(), 24);
+        assert_eq!(job.total_memory_megabytes(0), 2400);
+    }
+
+    #[test]
+    fn total_resources_account_for_array_tasks() {
+        let array = |spec: &str| {
+            SlurmJobBuilder::new(String::from("sleep 5"))
+                .set_cpus(2)
+                .set_memory(MegaByte(100))
+                .set_array(spec.to_string())
+                .build()
+        };
+        assert_eq!(array("0-99").total_cpus(), 200);
+        assert_eq!(array("0-99").total_memory_megabytes(0), 10000);
+        assert_eq!(array("1,3,10-20:5").total_cpus(), 10);
+        assert_eq!(array("0-99%4").total_cpus(), 8);
+    }
+
+    #[test]
+    fn cpu_budget_counts_array_tasks() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_max_total_cpus(16);
+        let array = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_array("0-9".to_string())
+            .build();
+        manager.add_jobs(vec![array.clone(), array]);
+        assert_eq!(manager.tick().submitted, 1);
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn cpu_budget_counts_ntasks_per_node() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_max_total_cpus(8);
+        let wide = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_nodes(2)
+            .set_ntasks_per_node(3)
+            .build();
+        manager.add_jobs(vec![wide.clone(), wide]);
+        assert_eq!(manager.tick().submitted, 1);
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn next_open_job_on_empty_queue_returns_none() {
+        let mut manager = SlurmManager::new(3);
+        assert!(manager.next_open_job(&[]).is_none());
+    }
+
+    #[test]
+    fn cancel_job_rejects_unknown_number() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&sleep_job(None));
+        match manager.cancel_job(42) {
+            Err(SlurmInteractionError::UnknownJob(42)) => {}
+            other => panic!("expected UnknownJob(42), got {:?}", other),
+        }
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn cancel_all_moves_open_jobs_to_finished() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_jobs(Vec::from([sleep_job(None), sleep_job(None)]));
+        manager
+            .cancel_all()
+            .expect("nothing was submitted to SLURM");
+        assert!(manager.open_jobs.is_empty());
+        assert_eq!(manager.finished_jobs.len(), 2);
+        assert!(
+            manager
+                .finished_jobs
+                .iter()
+                .all(|job| job.status() == CANCELLED)
+        );
+    }
+
+    #[test]
+    fn cancel_all_on_empty_manager_succeeds() {
+        let mut manager = SlurmManager::new(1);
+        assert!(manager.cancel_all().is_ok());
+        assert!(manager.finished_jobs.is_empty());
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn cancel_all_stops_scheduled_jobs() {
+        let mut manager = SlurmManager::new(2);
+        manager.add_jobs(Vec::from([
+            SlurmJobBuilder::new(String::from("sleep 30")).build(),
+            SlurmJobBuilder::new(String::from("sleep 30")).build(),
+        ]));
+        manager.fill_up_queue().expect("fill up queue");
+        manager.cancel_all().expect("cancel all");
+        assert!(manager.scheduled_jobs.is_empty());
+        assert_eq!(manager.successful_jobs(), 0);
+    }
+
+    #[test]
+    fn parse_job_number_of_plain_job() {
+        assert_eq!(SlurmManager::parse_job_number("12345"), Some(12345));
+        assert_eq!(SlurmManager::parse_job_number("abc"), None);
+    }
+
+    #[test]
+    fn parse_job_number_of_array_elements() {
+        assert_eq!(SlurmManager::parse_job_number("12345_3"), Some(12345));
+        assert_eq!(SlurmManager::parse_job_number("12345_[4-9]"), Some(12345));
+        assert_eq!(SlurmManager::parse_job_number("12345_[4-9%2]"), Some(12345));
+    }
+
+    #[test]
+    fn parse_job_number_of_het_components() {
+        assert_eq!(SlurmManager::parse_job_number("123+0"), Some(123));
+        assert_eq!(SlurmManager::parse_job_number("123+1"), Some(123));
+        assert_eq!(SlurmManager::parse_job_number("+1"), None);
+    }
+
+    #[test]
+    fn parse_squeue_output_groups_het_components() {
+        let out = "123+0 normal prep user PD 0:00 1 (JobHeldUser)\n\
+            123+1 gpu train user PD 0:00 1 (JobHeldUser)\n\
+            124+0 normal prep user R 0:05 1 node01\n\
+            124+1 gpu train user R 0:05 1 gpu01\n";
+        let jobs = SlurmManager::parse_squeue_output(out);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(
+            SlurmManager::live_status(&jobs[&123].state, &jobs[&123].reason),
+            Some(HELD)
+        );
+        assert_eq!(jobs[&124].state, "R");
+    }
+
+    #[test]
+    fn parse_squeue_output_groups_array_elements() {
+        let out = "100 normal a user R 0:05 1 node01\n\
+            200_1 normal b user R 0:03 1 node02\n\
+            200_2 normal b user R 0:03 1 node03\n\
+            200_[3-9] normal b user PD 0:00 1 (Resources)\n";
+        let running = SlurmManager::parse_squeue_output(out);
+        assert_eq!(running.len(), 2);
+        assert_eq!(running[&100].state, "R");
+        assert_eq!(running[&200].state, "R");
+    }
+
+    #[test]
+    fn parse_squeue_row_keeps_reason_intact() {
+        let row = "  4242   normal  job  user PD 0:00 1 (QOSMaxJobsPerUserLimit, Priority)";
+        let parsed = SlurmManager::parse_squeue_row(row).expect("valid row");
+        assert_eq!(parsed.number, 4242);
+        assert_eq!(parsed.state, "PD");
+        assert_eq!(parsed.reason, "(QOSMaxJobsPerUserLimit, Priority)");
+    }
+
+    #[test]
+    fn parse_squeue_row_running_job() {
+        let parsed = SlurmManager::parse_squeue_row("17 normal job user R 1:23 2 node[01-02]")
+            .expect("valid row");
+        assert_eq!(parsed.number, 17);
+        assert_eq!(parsed.nodes, 2);
+        assert_eq!(parsed.reason, "node[01-02]");
+    }
+
+    #[test]
+    fn parse_squeue_row_rejects_malformed_rows() {
+        assert!(matches!(
+            SlurmManager::parse_squeue_row("17 normal job"),
+            Err(SlurmInteractionError::BadSqueueRow(_))
+        ));
+        assert!(SlurmManager::parse_squeue_row("x normal job user R 1:23 2 node01").is_err());
+        assert!(SlurmManager::parse_squeue_row("17 normal job user R 1:23 two node01").is_err());
+    }
+
+    #[test]
+    fn parse_squeue_output_without_any_output() {
+        assert!(SlurmManager::parse_squeue_output("").is_empty());
+        assert!(SlurmManager::parse_squeue_output("\n").is_empty());
+    }
+
+    #[test]
+    fn parse_squeue_output_skips_cluster_line() {
+        let out = "CLUSTER: north\n12 main job user R 0:05 1 node01\n";
+        let jobs = SlurmManager::parse_squeue_output(out);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[&12].state, "R");
+    }
+
+    #[test]
+    fn parse_squeue_output_keeps_first_row_of_noheader_dump() {
+        let out = "7 normal a user PD 0:00 1 (Priority)\n8 normal b user R 0:05 1 node01\n";
+        let jobs = SlurmManager::parse_squeue_output(out);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[&7].reason, "(Priority)");
+        assert_eq!(jobs[&8].state, "R");
+    }
+
+    #[test]
+    fn parse_squeue_output_skips_stray_header() {
+        let header = "JOBID PARTITION NAME USER ST TIME NODES NODELIST(REASON)";
+        assert!(SlurmManager::parse_squeue_output(header).is_empty());
+        assert!(SlurmManager::parse_squeue_output(&format!("{}\n", header)).is_empty());
+    }
+
+    #[test]
+    fn get_running_jobs_reads_noheader_dump() {
+        let mut manager = SlurmManager::new(1);
+        let calls = recording_backend(&mut manager, "3 main job user R 0:01 1 node01\n");
+        let running = manager.get_running_jobs().expect("polled");
+        assert_eq!(running[&3].state, "R");
+        assert!(
+            calls.lock().unwrap()[0]
+                .1
+                .contains(&"--noheader".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_squeue_output_moves_jobs_on_to_sacct() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[
+            ("squeue", "exit 0"),
+            ("sacct", "echo 'COMPLETED|0:0'"),
+        ]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        assert_eq!(manager.check_on_jobs().unwrap(), 1);
+        assert_eq!(manager.finished_jobs[0].status(), FINISHED);
+    }
+
+    #[test]
+    fn failing_squeue_is_reported_as_unresponsive() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[
+            (
+                "squeue",
+                "echo 'squeue: error: Unable to contact slurm controller' >&2; exit 1",
+            ),
+            ("sacct", "echo 'COMPLETED|0:0'"),
+        ]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        match manager.check_on_jobs() {
+            Err(SlurmInteractionError::SlurmUnresponsive(why)) => {
+                assert!(why.contains("Unable to contact"))
+            }
+            other => panic!("expected SlurmUnresponsive, got {:?}", other),
+        }
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+        assert!(manager.finished_jobs.is_empty());
+    }
+
+    #[test]
+    fn squeue_with_only_forgotten_jobs_counts_as_empty_queue() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[
+            (
+                "squeue",
+                "echo 'slurm_load_jobs error: Invalid job id specified' >&2; exit 1",
+            ),
+            ("sacct", "echo 'COMPLETED|0:0'"),
+        ]));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        assert_eq!(manager.check_on_jobs().unwrap(), 1);
+        assert_eq!(manager.finished_jobs[0].status(), FINISHED);
+    }
+
+    #[test]
+    fn hanging_squeue_is_reported_as_unresponsive() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[("squeue", "exec sleep 5")]));
+        manager.set_command_timeout(Duration::from_millis(100));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        assert!(matches!(
+            manager.check_on_jobs(),
+            Err(SlurmInteractionError::SlurmUnresponsive(_))
+        ));
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+    }
+
+    #[test]
+    fn command_timeout_carries_over_to_a_new_backend() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_command_timeout(Duration::from_millis(100));
+        manager.set_binary_path(fake_slurm_dir(&[("sbatch", "exec sleep 5")]));
+        manager.add_job(&sleep_job(None));
+        assert!(manager.fill_up_queue().is_err());
+        assert_eq!(manager.open_jobs[0].failed_submissions, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "command timeout must not be zero")]
+    fn set_command_timeout_rejects_zero() {
+        SlurmManager::new(1).set_command_timeout(Duration::ZERO);
+    }
+
+    #[test]
+    fn parse_squeue_output_skips_malformed_rows() {
+        let out = "garbage\n\
+            JOBID PARTITION NAME USER ST TIME NODES NODELIST(REASON)\n\
+            100 normal a user R 0:05 1 node01\n";
+        let running = SlurmManager::parse_squeue_output(out);
+        assert_eq!(running.keys().collect::<Vec<_>>(), vec![&100]);
+    }
+
+    #[test]
+    fn live_status_maps_squeue_states() {
+        assert_eq!(SlurmManager::live_status("PD", "(Priority)"), Some(QUEUED));
+        assert_eq!(SlurmManager::live_status("R", "node01"), Some(RUNNING));
+        assert_eq!(SlurmManager::live_status("CG", "node01"), Some(RUNNING));
+        assert_eq!(SlurmManager::live_status("S", "node01"), None);
+    }
+
+    #[test]
+    fn pending_reason_strips_parentheses() {
+        assert_eq!(
+            SlurmManager::pending_reason("PD", "(QOSMaxJobsPerUserLimit)").as_deref(),
+            Some("QOSMaxJobsPerUserLimit")
+        );
+        assert_eq!(
+            SlurmManager::pending_reason("PD", "(Resources)").as_deref(),
+            Some("Resources")
+        );
+    }
+
+    #[test]
+    fn pending_reason_ignores_node_list_of_running_jobs() {
+        assert_eq!(SlurmManager::pending_reason("R", "node[01-04]"), None);
+    }
+
+    #[test]
+    fn live_status_recognises_held_jobs() {
+        assert_eq!(SlurmManager::live_status("PD", "(JobHeldUser)"), Some(HELD));
+        assert_eq!(
+            SlurmManager::live_status("PD", "(JobHeldAdmin)"),
+            Some(HELD)
+        );
+    }
+
+    #[test]
+    fn update_live_status_transitions_scheduled_job() {
+        let mut manager = SlurmManager::new(1);
+        let transitions = record_transitions(&mut manager);
+        let mut job = sleep_job(None);
+        job.set_number(5);
+        job.set_status(SUBMITTED);
+        manager.scheduled_jobs.push(job);
+        let queued =
+            SlurmManager::parse_squeue_row("5 normal job user PD 0:00 1 (Priority)").unwrap();
+        let running = SlurmManager::parse_squeue_row("5 normal job user R 0:01 1 node01").unwrap();
+        manager.update_live_status(0, &queued);
+        manager.update_live_status(0, &queued);
+        manager.update_live_status(0, &running);
+        assert_eq!(manager.scheduled_jobs[0].status(), RUNNING);
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![QUEUED, RUNNING],
+            "unchanged states must not fire a transition"
+        );
+    }
+
+    #[test]
+    fn parse_squeue_output_prefers_running_array_element() {
+        let out = "300_[2-9] normal b user PD 0:00 1 (Resources)\n\
+            300_1 normal b user R 0:03 1 node02\n";
+        assert_eq!(SlurmManager::parse_squeue_output(out)[&300].state, "R");
+    }
+
+    #[test]
+    fn parse_sacct_output_uses_job_row() {
+        let out = "TIMEOUT|0:15\nCANCELLED|0:15\nCOMPLETED|0:0\n";
+        let (state, exit_code) = SlurmManager::parse_sacct_output(out).expect("parsable");
+        assert_eq!(state, SlurmJobState::Timeout);
+        assert_eq!(exit_code, "0:15");
+    }
+
+    #[test]
+    fn parse_sacct_output_aggregates_het_components() {
+        let failed = "COMPLETED|0:0\nFAILED|1:0\n";
+        assert_eq!(
+            SlurmManager::parse_sacct_output(failed),
+            Some((SlurmJobState::Failed, "1:0".to_string()))
+        );
+        let active = "COMPLETED|0:0\nRUNNING|0:0\n";
+        assert_eq!(
+            SlurmManager::parse_sacct_output(active).map(|(state, _)| state),
+            Some(SlurmJobState::Running)
+        );
+        let completed = "COMPLETED|0:0\nCOMPLETED|0:0\n";
+        assert_eq!(
+            SlurmManager::parse_sacct_output(completed).map(|(state, _)| state),
+            Some(SlurmJobState::Completed)
+        );
+    }
+
+    #[test]
+    fn parse_sacct_output_completed() {
+        let (state, exit_code) =
+            SlurmManager::parse_sacct_output("COMPLETED|0:0\n").expect("parsable");
+        assert_eq!(state, SlurmJobState::Completed);
+        assert_eq!(exit_code, "0:0");
+    }
+
+    #[test]
+    fn parse_sacct_output_without_record() {
+        assert!(SlurmManager::parse_sacct_output("").is_none());
+        assert!(SlurmManager::parse_sacct_output("garbage\n").is_none());
+    }
+
+    #[test]
+    fn poll_interval_defaults_to_five_seconds() {
+        let manager = SlurmManager::new(1);
+        assert_eq!(manager.poll_interval, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn set_poll_interval_overrides_default() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_poll_interval(Duration::from_millis(250));
+        assert_eq!(manager.poll_interval, Duration::from_millis(250));
+    }
+
+    #[test]
+    #[should_panic(expected = "poll interval must be greater than zero")]
+    fn set_poll_interval_rejects_zero() {
+        SlurmManager::new(1).set_poll_interval(Duration::ZERO);
+    }
+
+    #[test]
+    #[serial]
+    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
+    fn short_poll_interval_finishes_quickly() {
+        let job = SlurmJobBuilder::new(String::from("sleep 1")).build();
+        let mut manager = SlurmManager::new(1);
+        manager.set_poll_interval(Duration::from_secs(1));
+        manager.add_job(&job);
+        let started = Local::now();
+        assert_eq!(manager.manage_jobs(Some(20)), Ok(true));
+        assert!(
+            Local::now() - started < TimeDelta::seconds(10),
+            "a one second poll interval should notice the finished job quickly"
+        );
+    }
+
+    #[test]
+    fn retry_backoff_grows_exponentially() {
+        assert_eq!(SlurmManager::retry_backoff(1), TimeDelta::seconds(5));
+        assert_eq!(SlurmManager::retry_backoff(2), TimeDelta::seconds(10));
+        assert_eq!(SlurmManager::retry_backoff(4), TimeDelta::seconds(40));
+    }
+
+    #[test]
+    fn failed_submission_is_requeued_with_backoff() {
+        let mut manager = SlurmManager::new(1);
+        manager.handle_failed_submission(sleep_job(None));
+        assert_eq!(manager.open_jobs.len(), 1);
+        assert_eq!(manager.open_jobs[0].failed_submissions, 1);
+        assert!(manager.open_jobs[0].retry_after.is_some());
+        assert!(
+            manager.next_open_job(&[]).is_none(),
+            "a job that is backing off must not be picked up right away"
+        );
+    }
+
+    #[test]
+    fn backing_off_job_does_not_block_others() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&SlurmJobBuilder::new("echo waiting".to_string()).build());
+        manager.handle_failed_submission(SlurmJobBuilder::new("echo retry".to_string()).build());
+        let next = manager
+            .next_open_job(&[])
+            .expect("the other job is eligible");
+        assert_eq!(next.command, "echo waiting");
+    }
+
+    #[test]
+    fn job_exceeding_max_retries_is_failed() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_max_retries(0);
+        manager.handle_failed_submission(sleep_job(None));
+        assert!(manager.open_jobs.is_empty());
+        assert_eq!(manager.finished_jobs.len(), 1);
+        assert_eq!(manager.finished_jobs[0].status(), FAILED);
+    }
+
+    #[test]
+    fn add_job_returns_distinct_handles() {
+        let mut manager = SlurmManager::new(1);
+        let job = sleep_job(None);
+        let first = manager.add_job(&job);
+        let second = manager.add_job(&job);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn job_number_is_none_before_submission() {
+        let mut manager = SlurmManager::new(1);
+        let handle = manager.add_job(&sleep_job(None));
+        assert_eq!(manager.job_number(handle), None);
+        assert_eq!(manager.job_number(JobHandle(99)), None);
+    }
+
+    #[test]
+    fn job_number_follows_job_through_buckets() {
+        let mut manager = SlurmManager::new(1);
+        let handle = manager.add_job(&sleep_job(None));
+        let mut job = manager.next_open_job(&[]).expect("job is eligible");
+        job.set_number(1234);
+        manager.scheduled_jobs.push(job);
+        assert_eq!(manager.job_number(handle), Some(1234));
+        let mut finished = manager.scheduled_jobs.remove(0);
+        finished.set_status(FINISHED);
+        manager.finished_jobs.push(finished);
+        assert_eq!(manager.job_number(handle), Some(1234));
+    }
+
+    #[test]
+    fn dependent_waits_until_prerequisite_is_submitted() {
+        let mut manager = SlurmManager::new(2);
+        let first = manager.add_job(&SlurmJobBuilder::new("echo first".to_string()).build());
+        manager.add_job_with_dependency(
+            &SlurmJobBuilder::new("echo second".to_string()).build(),
+            &[first],
+        );
+        let mut prerequisite = manager
+            .next_open_job(&[])
+            .expect("prerequisite is eligible");
+        assert!(
+            manager.next_open_job(&[]).is_none(),
+            "dependent must wait while its prerequisite is not submitted"
+        );
+        prerequisite.set_number(100);
+        manager.scheduled_jobs.push(prerequisite);
+        let dependent = manager
+            .next_open_job(&[])
+            .expect("dependent is now eligible");
+        assert_eq!(dependent.after_ok, vec![100]);
+        assert!(
+            dependent
+                .generate_slurm_script()
+                .contains("#SBATCH --dependency=afterok:100\n")
+        );
+    }
+
+    #[test]
+    fn finished_prerequisite_is_not_listed_as_dependency() {
+        let mut manager = SlurmManager::new(2);
+        let first = manager.add_job(&sleep_job(None));
+        manager.add_job_with_dependency(&sleep_job(None), &[first]);
+        let mut prerequisite = manager
+            .next_open_job(&[])
+            .expect("prerequisite is eligible");
+        prerequisite.set_status(FINISHED);
+        manager.finished_jobs.push(prerequisite);
+        let dependent = manager.next_open_job(&[]).expect("dependent is eligible");
+        assert!(dependent.after_ok.is_empty());
+        assert!(!dependent.generate_slurm_script().contains("--dependency"));
+    }
+
+    #[test]
+    fn dependents_of_crashed_job_are_skipped() {
+        let mut manager = SlurmManager::new(2);
+        let first = manager.add_job(&sleep_job(None));
+        let second = manager.add_job_with_dependency(&sleep_job(None), &[first]);
+        manager.add_job_with_dependency(&sleep_job(None), &[second]);
+        let mut prerequisite = manager
+            .next_open_job(&[])
+            .expect("prerequisite is eligible");
+        prerequisite.set_status(CRASHED);
+        manager.finished_jobs.push(prerequisite);
+        manager.skip_broken_dependents();
+        assert!(manager.open_jobs.is_empty());
+        assert_eq!(
+            manager
+                .finished_jobs
+                .iter()
+                .filter(|job| job.status() == SKIPPED)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown dependency")]
+    fn add_job_with_unknown_dependency_panics() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job_with_dependency(&sleep_job(None), &[JobHandle(7)]);
+    }
+
+    #[test]
+    fn finished_jobs_exposes_all_outcomes() {
+        let mut manager = SlurmManager::new(1);
+        for status in [FINISHED, CRASHED, CANCELLED] {
+            let mut job = sleep_job(None);
+            job.set_status(status);
+            manager.finished_jobs.push(job);
+        }
+        assert_eq!(manager.finished_jobs().len(), 3);
+        assert_eq!(manager.finished_jobs()[0].command(), "sleep 5");
+    }
+
+    #[test]
+    fn summary_of_empty_manager() {
+        assert_eq!(SlurmManager::new(1).summary(), Summary::default());
+    }
+
+    #[test]
+    fn summary_counts_jobs_in_every_stage() {
+        let mut manager = SlurmManager::new(2);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        manager.scheduled_jobs.push(submitted_job(1, 0));
+        for status in [FINISHED, CRASHED, TIMEOUT] {
+            let mut job = sleep_job(None);
+            job.set_status(status);
+            manager.finished_jobs.push(job);
+        }
+        let summary = manager.summary();
+        assert_eq!(summary.open, 2);
+        assert_eq!(summary.scheduled, 1);
+        assert_eq!(summary.finished, 3);
+        assert_eq!(summary.crashed, 2);
+        assert_eq!(summary.total, 6);
+    }
+
+    #[test]
+    fn failed_jobs_contains_crashed_and_unsubmitted_jobs() {
+        let mut manager = SlurmManager::new(1);
+        for (command, status) in [
+            ("echo ok", FINISHED),
+            ("echo crash", CRASHED),
+            ("echo reject", FAILED),
+        ] {
+            let mut job = SlurmJobBuilder::new(command.to_string()).build();
+            job.set_status(status);
+            manager.finished_jobs.push(job);
+        }
+        let failed: Vec<&str> = manager
+            .failed_jobs()
+            .iter()
+            .map(|job| job.command())
+            .collect();
+        assert_eq!(failed, vec!["echo crash", "echo reject"]);
+    }
+
+    #[test]
+    fn failed_jobs_empty_without_finished_jobs() {
+        let manager = SlurmManager::new(1);
+        assert!(manager.finished_jobs().is_empty());
+        assert!(manager.failed_jobs().is_empty());
+    }
+
+    #[test]
+    fn squeue_args_select_scheduled_jobs() {
+        let mut manager = SlurmManager::new(2);
+        manager.scheduled_jobs.push(submitted_job(17, 0));
+        manager.scheduled_jobs.push(submitted_job(23, 0));
+        assert_eq!(manager.squeue_args()[0], "--jobs=17,23");
+    }
+
+    #[test]
+    fn squeue_args_keep_the_format() {
+        let mut manager = SlurmManager::new(1);
+        manager.scheduled_jobs.push(submitted_job(17, 0));
+        assert_eq!(
+            manager.squeue_args()[1..],
+            ["--noheader", "--format", "%.i %.P %.j %.u %.t %.M %.D %R"]
+        );
+    }
+
+    #[test]
+    fn squeue_args_fall_back_to_own_jobs() {
+        assert_eq!(SlurmManager::new(1).squeue_args()[0], "--me");
+    }
+
+    type Calls = std::sync::Arc<std::sync::Mutex<Vec<(String, Vec<String>, Option<String>)>>>;
+
+    // answers every command with the same output and records what was run
+    struct RecordingBackend {
+        stdout: String,
+        calls: Calls,
+    }
+
+    impl SlurmBackend for RecordingBackend {
+        fn run(
+            &self,
+            program: &str,
+            args: &[String],
+            input: Option<&str>,
+        ) -> std::io::Result<std::process::Output> {
+            use std::os::unix::process::ExitStatusExt;
+            self.calls.lock().unwrap().push((
+                program.to_string(),
+                args.to_vec(),
+                input.map(String::from),
+            ));
+            Ok(std::process::Output {
+                status: std::process::ExitStatus::from_raw(0),
+                stdout: self.stdout.clone().into_bytes(),
+                stderr: Vec::new(),
+            })
+        }
+    }
+
+    fn recording_backend(manager: &mut SlurmManager, stdout: &str) -> Calls {
+        let calls = Calls::default();
+        manager.set_backend(RecordingBackend {
+            stdout: stdout.to_string(),
+            calls: calls.clone(),
+        });
+        calls
+    }
+
+    #[test]
+    fn cluster_is_passed_to_every_command() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_cluster("north".to_string());
+        let calls = recording_backend(&mut manager, "Submitted batch job 7 on cluster north\n");
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.estimated_start_times().expect("estimates");
+        manager.cancel_job(number).expect("cancelled");
+        manager.resource_report();
+        let calls = calls.lock().unwrap();
+        let programs: Vec<&str> = calls.iter().map(|call| call.0.as_str()).collect();
+        assert_eq!(programs, ["sbatch", "squeue", "scancel", "sacct"]);
+        assert!(calls.iter().all(|call| call.1[0] == "--clusters=north"));
+        assert_eq!(calls[2].1[1], "7");
+    }
+
+    #[test]
+    fn commands_have_no_cluster_by_default() {
+        let mut manager = SlurmManager::new(1);
+        let calls = recording_backend(&mut manager, "Submitted batch job 7\n");
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.tick();
+        let calls = calls.lock().unwrap();
+        assert!(calls[0].1.is_empty());
+        assert!(!calls[1].1.iter().any(|arg| arg.starts_with("--clusters")));
+    }
+
+    #[test]
+    fn backend_receives_the_script_on_submission() {
+        let mut manager = SlurmManager::new(1);
+        let calls = recording_backend(&mut manager, "Submitted batch job 7\n");
+        let mut job = sleep_job(None);
+        assert_eq!(manager.schedule_job(&mut job).expect("submitted"), 7);
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls[0].0, "sbatch");
+        assert!(calls[0].1.is_empty());
+        assert_eq!(calls[0].2, Some(job.generate_slurm_script()));
+    }
+
+    #[test]
+    fn backend_cancels_by_job_number() {
+        let mut manager = SlurmManager::new(1);
+        let calls = recording_backend(&mut manager, "");
+        manager.scheduled_jobs.push(submitted_job(9, 0));
+        manager.cancel_job(9).expect("cancelled");
+        assert_eq!(
+            calls.lock().unwrap()[0],
+            ("scancel".to_string(), vec!["9".to_string()], None)
+        );
+    }
+
+    #[test]
+    fn backend_reports_bad_submission_output() {
+        let mut manager = SlurmManager::new(1);
+        recording_backend(&mut manager, "sbatch: error: invalid partition\n");
+        match manager.schedule_job(&mut sleep_job(None)) {
+            Err(SlurmInteractionError::BadSbatchResponse { .. }) => {}
+            other => panic!("expected BadSbatchResponse, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_binary_reports_unresponsive_slurm() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        match manager.get_running_jobs() {
+            Err(SlurmInteractionError::SlurmUnresponsive(_)) => {}
+            other => panic!("expected SlurmUnresponsive, got {:?}", other),
+        }
+    }
+
+    // directory with stand-ins for SLURM binaries, each given as the body of
+    // a shell script
+    fn fake_slurm_dir(binaries: &[(&str, &str)]) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("fake_slurm_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, body) in binaries {
+            let binary = dir.join(name);
+            std::fs::write(&binary, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        dir
+    }
+
+    // directory with a stand-in sbatch that accepts every script
+    fn fake_sbatch_dir() -> PathBuf {
+        fake_slurm_dir(&[("sbatch", "echo \"Submitted batch job 42\"")])
+    }
+
+    // sbatch accepts every script as job 42, squeue reports an empty queue and
+    // sacct reports the given state for every job
+    fn fake_cluster(sacct_state: &str) -> PathBuf {
+        fake_slurm_dir(&[
+            ("sbatch", "echo \"Submitted batch job 42\""),
+            ("squeue", "exit 0"),
+            ("sacct", &format!("echo \"{}\"", sacct_state)),
+        ])
+    }
+
+    #[test]
+    fn tick_submits_and_then_finishes_a_job() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_cluster("COMPLETED|0:0"));
+        manager.add_job(&sleep_job(None));
+        let first = manager.tick();
+        assert_eq!(
+            (first.finished, first.submitted, first.remaining),
+            (0, 1, 1)
+        );
+        assert!(first.work_remaining());
+        let second = manager.tick();
+        assert_eq!(
+            (second.finished, second.submitted, second.remaining),
+            (1, 0, 0)
+        );
+        assert!(!second.work_remaining());
+        assert_eq!(manager.successful_jobs(), 1);
+    }
+
+    #[test]
+    fn tick_counts_crashed_jobs_as_finished() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_cluster("FAILED|1:0"));
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        assert_eq!(manager.tick().finished, 1);
+        assert_eq!(manager.failed_jobs().len(), 1);
+    }
+
+    #[test]
+    fn tick_keeps_work_when_slurm_is_unreachable() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        manager.add_job(&sleep_job(None));
+        let result = manager.tick();
+        assert_eq!(
+            (result.finished, result.submitted, result.remaining),
+            (0, 0, 1)
+        );
+        assert_eq!(manager.open_jobs[0].failed_submissions, 1);
+    }
+
+    #[test]
+    fn manage_jobs_runs_until_all_jobs_finished() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_cluster("COMPLETED|0:0"));
+        manager.set_poll_interval(Duration::from_millis(10));
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        assert_eq!(manager.manage_jobs(Some(10)), Ok(true));
+        assert_eq!(manager.successful_jobs(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn manage_jobs_async_runs_until_all_jobs_finished() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_cluster("COMPLETED|0:0"));
+        manager.set_poll_interval(Duration::from_millis(10));
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        let (manager, done) = runtime.block_on(manager.manage_jobs_async(Some(10)));
+        assert_eq!(done, Ok(true));
+        assert_eq!(manager.successful_jobs(), 2);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn manage_jobs_async_runs_on_current_thread_runtime() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_cluster("COMPLETED|0:0"));
+        manager.set_poll_interval(Duration::from_millis(10));
+        manager.add_job(&sleep_job(None));
+        let (manager, done) = manager.manage_jobs_async(Some(10)).await;
+        assert_eq!(done, Ok(true));
+        assert_eq!(manager.successful_jobs(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn manage_jobs_async_stops_when_time_is_up() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        manager.set_poll_interval(Duration::from_millis(10));
+        manager.add_job(&sleep_job(None));
+        let (manager, done) = runtime.block_on(manager.manage_jobs_async(Some(0)));
+        assert_eq!(done, Ok(false));
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    fn sbatch_output(code: i32, stdout: &str, stderr: &str) -> Output {
+        use std::os::unix::process::ExitStatusExt;
+        Output {
+            status: std::process::ExitStatus::from_raw(code << 8),
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn error_kind_names_the_variant() {
+        assert_eq!(
+            SlurmInteractionError::SlurmUnresponsive(String::new()).kind(),
+            "SlurmUnresponsive"
+        );
+        assert_eq!(SlurmInteractionError::UnknownJob(3).kind(), "UnknownJob");
+    }
+
+    #[test]
+    fn error_kind_ignores_the_details() {
+        let rejected = |stderr: &str| SlurmInteractionError::BadSbatchResponse {
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+            exit_code: Some(1),
+        };
+        assert_eq!(rejected("Invalid account").kind(), rejected("").kind());
+        assert_ne!(
+            rejected("").kind(),
+            SlurmInteractionError::BadScancelResponse(String::new()).kind()
+        );
+    }
+
+    #[test]
+    fn parse_sbatch_output_returns_job_number() {
+        let output = sbatch_output(0, "Submitted batch job 4711\n", "");
+        assert_eq!(SlurmManager::parse_sbatch_output(&output).unwrap(), 4711);
+    }
+
+    #[test]
+    fn parse_sbatch_output_with_cluster() {
+        let output = sbatch_output(0, "Submitted batch job 4711 on cluster north\n", "");
+        assert_eq!(SlurmManager::parse_sbatch_output(&output).unwrap(), 4711);
+    }
+
+    #[test]
+    fn parse_sbatch_output_keeps_stderr_and_exit_code() {
+        let output = sbatch_output(
+            1,
+            "",
+            "sbatch: error: Batch job submission failed: Invalid account or account/partition combination specified\n",
+        );
+        match SlurmManager::parse_sbatch_output(&output) {
+            Err(SlurmInteractionError::BadSbatchResponse {
+                stdout,
+                stderr,
+                exit_code,
+            }) => {
+                assert_eq!(stdout, "");
+                assert!(
+                    stderr.ends_with("Invalid account or account/partition combination specified")
+                );
+                assert_eq!(exit_code, Some(1));
+            }
+            other => panic!("expected BadSbatchResponse, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_sbatch_output_rejects_failed_exit_with_number() {
+        let output = sbatch_output(2, "Submitted batch job 12\n", "sbatch: warning: odd\n");
+        assert!(SlurmManager::parse_sbatch_output(&output).is_err());
+    }
+
+    fn local_time(time: &str) -> DateTime<Local> {
+        NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S")
+            .unwrap()
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_start_times_skips_missing_estimates() {
+        let out = "101 2024-01-01T02:00:00\n102 N/A\n\n";
+        let start_times = SlurmManager::parse_start_times(out);
+        assert_eq!(start_times.len(), 1);
+        assert_eq!(start_times[&101], local_time("2024-01-01T02:00:00"));
+    }
+
+    #[test]
+    fn parse_start_times_keeps_earliest_array_task() {
+        let out = "200_1 2024-01-01T03:00:00\n200_[2-9] 2024-01-01T01:30:00\n";
+        assert_eq!(
+            SlurmManager::parse_start_times(out)[&200],
+            local_time("2024-01-01T01:30:00")
+        );
+    }
+
+    #[test]
+    fn estimated_start_times_queries_scheduled_jobs() {
+        let mut manager = SlurmManager::new(2);
+        let calls = recording_backend(&mut manager, "5 2030-06-01T08:00:00\n6 N/A\n");
+        assert!(manager.estimated_start_times().unwrap().is_empty());
+        assert!(calls.lock().unwrap().is_empty());
+        manager.scheduled_jobs.push(submitted_job(5, 0));
+        manager.scheduled_jobs.push(submitted_job(6, 0));
+        let start_times = manager.estimated_start_times().unwrap();
+        assert_eq!(start_times.len(), 1);
+        assert_eq!(start_times[&5], local_time("2030-06-01T08:00:00"));
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls[0].0, "squeue");
+        assert_eq!(calls[0].1[..2], ["--jobs=5,6", "--start"]);
+    }
+
+    #[test]
+    fn failing_squeue_start_is_reported() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        mock.fail_squeue(1);
+        let error = manager.estimated_start_times().expect_err("squeue failed");
+        assert_eq!(
+            error,
+            SlurmInteractionError::BadSqueueResponse(String::from(
+                "squeue: error: Invalid partition name specified"
+            ))
+        );
+        assert!(!error.is_transient());
+        assert!(manager.estimated_start_times().is_ok());
+    }
+
+    fn mock_manager(max_queue: i32) -> (SlurmManager, MockScheduler) {
+        let mock = MockScheduler::new();
+        (SlurmManager::with_backend(max_queue, mock.clone()), mock)
+    }
+
+    #[test]
+    fn mock_scheduler_fills_queue_in_order_up_to_max_queue() {
+        let (mut manager, mock) = mock_manager(2);
+        let commands = ["echo 1", "echo 2", "echo 3"];
+        for command in commands {
+            manager.add_job(&SlurmJobBuilder::new(command.to_string()).build());
+        }
+        assert_eq!(manager.tick().submitted, 2);
+        assert_eq!(mock.queued_jobs(), [1, 2]);
+        assert!(mock.submitted_scripts()[0].contains("\necho 1\n"));
+        assert!(mock.submitted_scripts()[1].contains("\necho 2\n"));
+        mock.finish(1, "COMPLETED", "0:0");
+        let result = manager.tick();
+        assert_eq!((result.finished, result.submitted), (1, 1));
+        assert!(mock.submitted_scripts()[2].contains("\necho 3\n"));
+    }
+
+    #[test]
+    fn mock_scheduler_reports_live_and_final_status() {
+        let (mut manager, mock) = mock_manager(1);
+        let transitions = record_transitions(&mut manager);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        manager.tick();
+        mock.start(1);
+        manager.tick();
+        mock.finish(1, "COMPLETED", "0:0");
+        assert!(!manager.tick().work_remaining());
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            [PENDING, SUBMITTED, QUEUED, RUNNING, FINISHED]
+        );
+    }
+
+    #[test]
+    fn submit_now_bypasses_the_queue_limit() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        assert_eq!(number, 2);
+        assert_eq!(mock.queued_jobs(), [1, 2]);
+        assert_eq!(manager.scheduled_jobs.len(), 2);
+        assert_eq!(manager.scheduled_jobs[1].status(), SUBMITTED);
+        assert!(manager.scheduled_jobs[1].submitted_at().is_some());
+    }
+
+    #[test]
+    fn submit_now_jobs_are_tracked_until_finished() {
+        let (mut manager, mock) = mock_manager(1);
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        assert_eq!(manager.job_number(JobHandle(0)), Some(number));
+        mock.finish(number, "COMPLETED", "0:0");
+        let result = manager.tick();
+        assert_eq!(result.finished, 1);
+        assert!(!result.work_remaining());
+        assert_eq!(manager.finished_jobs()[0].status(), FINISHED);
+    }
+
+    #[test]
+    fn submit_now_rejection_is_reported_and_not_tracked() {
+        let (mut manager, mock) = mock_manager(1);
+        mock.reject_submissions(1);
+        assert!(matches!(
+            manager.submit_now(&sleep_job(None)),
+            Err(SlurmInteractionError::BadSbatchResponse { .. })
+        ));
+        assert!(manager.scheduled_jobs.is_empty() && manager.open_jobs.is_empty());
+        assert_eq!(manager.add_job(&sleep_job(None)), JobHandle(1));
+    }
+
+    #[test]
+    fn submit_now_rejection_is_reported_to_status_callbacks() {
+        let (mut manager, mock) = mock_manager(1);
+        let transitions = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = transitions.clone();
+        manager.set_on_status_change(move |job, status| {
+            recorder.lock().unwrap().push((job.handle, status))
+        });
+        mock.reject_submissions(1);
+        assert!(manager.submit_now(&sleep_job(None)).is_err());
+        manager.add_job(&sleep_job(None));
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![
+                (Some(JobHandle(0)), PENDING),
+                (Some(JobHandle(0)), FAILED),
+                (Some(JobHandle(1)), PENDING)
+            ]
+        );
+    }
+
+    #[test]
+    fn mock_scheduler_pending_reason_is_cleared_once_running() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        assert_eq!(manager.job_pending_reason(1), None);
+        manager.tick();
+        assert_eq!(manager.job_pending_reason(1), Some("Priority"));
+        assert_eq!(manager.scheduled_jobs[0].pending_reason(), Some("Priority"));
+        mock.start(1);
+        manager.tick();
+        assert_eq!(manager.job_pending_reason(1), None);
+        assert_eq!(manager.job_pending_reason(99), None);
+    }
+
+    #[test]
+    fn log_dir_is_used_for_unset_log_files() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.set_log_dir(PathBuf::from("/scratch/logs"));
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(script.contains("#SBATCH --output=/scratch/logs/%j.out\n"));
+        assert!(script.contains("#SBATCH --error=/scratch/logs/%j.err\n"));
+        let context = manager.scheduled_jobs[0].post_processing_context();
+        assert_eq!(
+            context.output_file(),
+            Some(&PathBuf::from(format!("/scratch/logs/{}.out", number)))
+        );
+    }
+
+    #[test]
+    fn log_dir_keeps_explicit_log_files() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.set_log_dir(PathBuf::from("/scratch/logs"));
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_file("/data/align.out".to_string())
+            .build();
+        manager.submit_now(&job).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(script.contains("#SBATCH --output=/data/align.out\n"));
+        assert!(script.contains("#SBATCH --error=/scratch/logs/%j.err\n"));
+    }
+
+    #[test]
+    fn log_files_are_discarded_without_log_dir() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(script.contains("#SBATCH --output=/dev/null\n"));
+        assert!(script.contains("#SBATCH --error=/dev/null\n"));
+    }
+
+    #[test]
+    fn manager_environment_is_merged_into_jobs() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_module("gcc/12".to_string());
+        manager.add_module("python/3.11".to_string());
+        manager.add_env("PROJECT".to_string(), "atlas".to_string());
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_module("python/3.11".to_string())
+            .add_module("samtools".to_string())
+            .add_env("THREADS".to_string(), "4".to_string())
+            .build();
+        manager.submit_now(&job).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(
+            script.contains("module load gcc/12\nmodule load python/3.11\nmodule load samtools\n")
+        );
+        assert!(script.contains("export PROJECT='atlas'\nexport THREADS='4'\n"));
+    }
+
+    #[test]
+    fn job_environment_takes_precedence_over_manager() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_env("OMP_NUM_THREADS".to_string(), "1".to_string());
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .add_env("OMP_NUM_THREADS".to_string(), "8".to_string())
+            .build();
+        manager.submit_now(&job).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(script.contains("export OMP_NUM_THREADS='8'\n"));
+        assert!(!script.contains("export OMP_NUM_THREADS='1'"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid environment variable name: 1PATH")]
+    fn manager_env_rejects_invalid_names() {
+        SlurmManager::new(1).add_env("1PATH".to_string(), "/opt".to_string());
+    }
+
+    #[test]
+    fn script_transform_changes_submitted_script() {
+        let (mut manager, mock) = mock_manager(1);
+        manager
+            .set_script_transform(|script| script.replace("\nsleep 5\n", "\nperf stat sleep 5\n"));
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        let script = &mock.submitted_scripts()[0];
+        assert!(script.starts_with("#!/bin/bash\n"));
+        assert!(script.contains("\nperf stat sleep 5\n"));
+    }
+
+    #[test]
+    fn script_transform_output_is_kept() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_sbatch_dir());
+        manager.set_keep_scripts(true);
+        manager.set_script_transform(|script| format!("{}# wrapped\n", script));
+        let mut job = sleep_job(None);
+        manager.schedule_job(&mut job).expect("submitted");
+        let kept = std::fs::read_to_string(script_path(&job)).unwrap();
+        assert_eq!(kept, format!("{}# wrapped\n", job.generate_slurm_script()));
+        let _ = std::fs::remove_file(script_path(&job));
+    }
+
+    #[test]
+    fn scripts_are_submitted_unchanged_without_transform() {
+        let (mut manager, mock) = mock_manager(1);
+        let job = sleep_job(None);
+        manager.submit_now(&job).expect("submitted");
+        assert_eq!(mock.submitted_scripts()[0], job.generate_slurm_script());
+    }
+
+    #[test]
+    fn resource_report_lists_usage_of_finished_jobs() {
+        let (mut manager, mock) = mock_manager(2);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        mock.finish(1, "COMPLETED", "0:0");
+        mock.record_usage(1, "01:00:00", "512M", "03:30:00");
+        manager.tick();
+        let report = manager.resource_report();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].number, 1);
+        assert_eq!(report[0].job_id, *manager.finished_jobs()[0].get_id());
+        assert_eq!(report[0].elapsed, Some(Duration::from_secs(3600)));
+        assert_eq!(report[0].max_rss, Some(MegaByte(512)));
+        assert_eq!(report[0].cpu_hours(), Some(3.5));
+    }
+
+    #[test]
+    fn resource_report_skips_jobs_never_submitted() {
+        let (mut manager, _mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.cancel_all().expect("nothing to scancel");
+        assert_eq!(manager.finished_jobs().len(), 1);
+        assert!(manager.resource_report().is_empty());
+    }
+
+    #[test]
+    fn resource_report_without_accounting_record() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.tick();
+        let report = manager.resource_report();
+        assert_eq!(report.len(), 1);
+        assert_eq!((report[0].elapsed, report[0].max_rss.clone()), (None, None));
+    }
+
+    #[test]
+    fn description_survives_into_finished_jobs() {
+        let (mut manager, mock) = mock_manager(1);
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_description(String::from("sample42/experiment7"))
+            .build();
+        manager.add_job(&job);
+        manager.tick();
+        assert_eq!(
+            manager.scheduled_jobs[0].description(),
+            "sample42/experiment7"
+        );
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.tick();
+        assert_eq!(
+            manager.finished_jobs()[0].description(),
+            "sample42/experiment7"
+        );
+    }
+
+    #[test]
+    fn description_defaults_to_empty() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5")).build();
+        assert_eq!(job.description(), "");
+    }
+
+    #[test]
+    fn description_is_not_part_of_the_script() {
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_description(String::from("sample42"))
+            .build();
+        assert!(!job.generate_slurm_script().contains("sample42"));
+    }
+
+    #[test]
+    fn concurrent_submission_fills_queue_up_to_max_queue() {
+        let (mut manager, mock) = mock_manager(4);
+        manager.set_submit_concurrency(3);
+        manager.add_jobs((0..6).map(|_| sleep_job(None)).collect());
+        assert_eq!(manager.fill_up_queue().expect("all submitted"), 4);
+        assert_eq!(mock.queued_jobs(), [1, 2, 3, 4]);
+        assert_eq!(manager.open_jobs.len(), 2);
+        let mut numbers: Vec<i32> = manager
+            .scheduled_jobs
+            .iter()
+            .map(|job| job.get_number())
+            .collect();
+        numbers.sort();
+        assert_eq!(numbers, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn concurrent_submission_respects_resource_budget() {
+        let (mut manager, _mock) = mock_manager(10);
+        manager.set_submit_concurrency(4);
+        manager.set_max_total_cpus(4);
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_cpus(2)
+            .build();
+        manager.add_jobs(vec![job.clone(), job.clone(), job]);
+        assert_eq!(manager.fill_up_queue().expect("all submitted"), 2);
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn concurrent_submission_collects_errors_per_job() {
+        let (mut manager, mock) = mock_manager(3);
+        manager.set_submit_concurrency(3);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None), sleep_job(None)]);
+        mock.reject_submissions(2);
+        let errors = manager.fill_up_queue().expect_err("two submissions fail");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+        assert_eq!(manager.open_jobs.len(), 2);
+        assert!(
+            manager
+                .open_jobs
+                .iter()
+                .all(|job| job.failed_submissions == 1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "submit concurrency must be at least 1")]
+    fn submit_concurrency_rejects_zero() {
+        SlurmManager::new(1).set_submit_concurrency(0);
+    }
+
+    #[test]
+    fn rerun_failed_resubmits_a_crashed_job() {
+        let (mut manager, mock) = mock_manager(1);
+        let handle = manager.add_job(&sleep_job(None));
+        manager.tick();
+        mock.finish(1, "NODE_FAIL", "0:0");
+        manager.tick();
+        let old_id = manager.finished_jobs()[0].get_id().clone();
+        manager.rerun_failed(handle).expect("crashed job rerun");
+        assert!(manager.finished_jobs().is_empty());
+        let job = &manager.open_jobs[0];
+        assert_eq!(job.status(), PENDING);
+        assert_ne!(*job.get_id(), old_id);
+        assert_eq!(manager.job_number(handle), None);
+        assert!(job.exit_code().is_none() && job.finished_at().is_none());
+        manager.tick();
+        assert_eq!(manager.job_number(handle), Some(2));
+    }
+
+    #[test]
+    fn rerun_failed_keeps_a_custom_id() {
+        let (mut manager, mock) = mock_manager(1);
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_id("sample-42".to_string())
+            .build();
+        let handle = manager.add_job(&job);
+        manager.tick();
+        mock.finish(1, "NODE_FAIL", "0:0");
+        manager.tick();
+        manager.rerun_failed(handle).expect("crashed job rerun");
+        assert_eq!(manager.open_jobs[0].get_id(), "sample-42");
+    }
+
+    #[test]
+    fn rerun_failed_rejects_successful_and_running_jobs() {
+        let (mut manager, mock) = mock_manager(2);
+        let done = manager.add_job(&sleep_job(None));
+        let running = manager.add_job(&sleep_job(None));
+        manager.tick();
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.tick();
+        assert!(matches!(
+            manager.rerun_failed(done),
+            Err(SlurmInteractionError::JobNotFailed(handle)) if handle == done
+        ));
+        assert!(matches!(
+            manager.rerun_failed(running),
+            Err(SlurmInteractionError::JobNotFailed(_))
+        ));
+        assert_eq!(manager.finished_jobs().len(), 1);
+    }
+
+    #[test]
+    fn rerun_failed_rejects_unknown_handle() {
+        let mut manager = SlurmManager::new(1);
+        assert!(matches!(
+            manager.rerun_failed(JobHandle(7)),
+            Err(SlurmInteractionError::UnknownHandle(JobHandle(7)))
+        ));
+    }
+
+    #[test]
+    fn tick_returns_submission_errors() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        mock.reject_submissions(1);
+        let result = manager.tick();
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.kind(), "BadSbatchResponse");
+        assert!(!error.is_transient());
+        assert!(manager.tick().errors.is_empty());
+    }
+
+    #[test]
+    fn tick_returns_transient_errors_of_unresponsive_slurm() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        manager.add_job(&sleep_job(None));
+        let result = manager.tick();
+        assert_eq!(result.errors.len(), 2);
+        assert!(
+            result
+                .errors
+                .iter()
+                .all(SlurmInteractionError::is_transient)
+        );
+    }
+
+    #[test]
+    fn errors_display_the_slurm_message() {
+        let rejected = SlurmInteractionError::BadSbatchResponse {
+            stdout: String::new(),
+            stderr: "sbatch: error: Invalid account".to_string(),
+            exit_code: Some(1),
+        };
+        assert_eq!(
+            rejected.to_string(),
+            "sbatch did not return a job number (exit code 1): sbatch: error: Invalid account"
+        );
+        assert_eq!(
+            SlurmInteractionError::UnknownJob(42).to_string(),
+            "no scheduled job has the number 42"
+        );
+        let error: Box<dyn std::error::Error> = Box::new(SlurmInteractionError::SlurmUnresponsive(
+            "timed out".to_string(),
+        ));
+        assert_eq!(error.to_string(), "SLURM did not respond: timed out");
+    }
+
+    #[test]
+    fn hold_job_keeps_queued_job_from_starting() {
+        let (mut manager, mock) = mock_manager(1);
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.tick();
+        manager.hold_job(number).expect("held");
+        assert_eq!(manager.scheduled_jobs[0].status(), HELD);
+        assert_eq!(mock.held_jobs(), [number]);
+        manager.release_job(number).expect("released");
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+    }
+
+    #[test]
+    fn requeue_job_puts_running_job_back_into_the_queue() {
+        let (mut manager, mock) = mock_manager(1);
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        mock.start(number);
+        manager.tick();
+        manager.requeue_job(number).expect("requeued");
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+        manager.tick();
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+        assert_eq!(manager.job_pending_reason(number), Some("Priority"));
+    }
+
+    #[test]
+    fn scontrol_passes_arguments_and_rejects_unknown_jobs() {
+        let mut manager = SlurmManager::new(1);
+        let calls = recording_backend(&mut manager, "JobId=7 JobName=sleep\n");
+        manager.scheduled_jobs.push(submitted_job(7, 0));
+        let out = manager.scontrol(&["show", "job"], 7).expect("shown");
+        assert_eq!(out, "JobId=7 JobName=sleep\n");
+        assert_eq!(calls.lock().unwrap()[0].1, ["show", "job", "7"]);
+        manager
+            .scontrol(&["update", "JobId={job}", "Comment=job-{job}"], 7)
+            .expect("updated");
+        assert_eq!(
+            calls.lock().unwrap()[1].1,
+            ["update", "JobId=7", "Comment=job-7"]
+        );
+        assert!(matches!(
+            manager.scontrol(&["hold"], 8),
+            Err(SlurmInteractionError::UnknownJob(8))
+        ));
+        assert_eq!(calls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn set_job_priority_updates_the_job() {
+        let (mut manager, mock) = mock_manager(1);
+        let number = manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.set_job_priority(number, 500).expect("updated");
+        assert_eq!(mock.priority(number), Some(500));
+        assert!(matches!(
+            manager.set_job_priority(number + 1, 500),
+            Err(SlurmInteractionError::UnknownJob(_))
+        ));
+    }
+
+    #[test]
+    fn mock_scheduler_jobs_record_their_timing() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        let job = &manager.scheduled_jobs[0];
+        assert!(job.submitted_at().is_some());
+        assert!(job.started_at().is_none());
+        mock.start(1);
+        manager.tick();
+        let started_at = manager.scheduled_jobs[0].started_at();
+        assert!(started_at.is_some());
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.tick();
+        let job = &manager.finished_jobs()[0];
+        assert_eq!(job.started_at(), started_at);
+        assert!(job.submitted_at() <= job.started_at());
+        assert!(job.started_at() <= job.finished_at());
+    }
+
+    #[test]
+    fn jobs_finishing_between_checks_have_no_start_time() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        mock.finish(1, "COMPLETED", "0:0");
+        manager.tick();
+        let job = &manager.finished_jobs()[0];
+        assert!(job.started_at().is_none());
+        assert!(job.finished_at().is_some());
+    }
+
+    #[test]
+    fn open_jobs_have_no_timing() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&sleep_job(None));
+        let job = &manager.open_jobs[0];
+        assert!(job.submitted_at().is_none() && job.finished_at().is_none());
+    }
+
+    #[test]
+    fn mock_scheduler_rejection_is_retried_without_blocking_others() {
+        let (mut manager, mock) = mock_manager(2);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        mock.reject_submissions(1);
+        let result = manager.tick();
+        assert_eq!(result.submitted, 1);
+        assert_eq!(manager.open_jobs[0].failed_submissions, 1);
+        assert_eq!(mock.queued_jobs(), [1]);
+        manager.open_jobs[0].retry_after = None;
+        assert_eq!(manager.tick().submitted, 1);
+        assert_eq!(mock.queued_jobs(), [1, 2]);
+    }
+
+    fn held_job() -> SlurmJob {
+        SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_held(true)
+            .build()
+    }
+
+    #[test]
+    fn mock_scheduler_held_job_runs_after_release() {
+        let (mut manager, mock) = mock_manager(2);
+        manager.add_job(&held_job());
+        manager.tick();
+        manager.tick();
+        assert_eq!(manager.scheduled_jobs[0].status(), HELD);
+        assert_eq!(manager.summary().held, 1);
+        manager.release_job(1).expect("job released");
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+        manager.tick();
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+        mock.start(1);
+        manager.tick();
+        assert_eq!(manager.scheduled_jobs[0].status(), RUNNING);
+    }
+
+    #[test]
+    fn release_job_keeps_status_of_jobs_that_were_not_held() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.tick();
+        mock.start(1);
+        manager.tick();
+        manager
+            .release_job(1)
+            .expect("scontrol accepts running jobs");
+        assert_eq!(manager.scheduled_jobs[0].status(), RUNNING);
+    }
+
+    #[test]
+    fn release_job_reports_unknown_and_rejected_jobs() {
+        let (mut manager, _mock) = mock_manager(1);
+        assert!(matches!(
+            manager.release_job(7),
+            Err(SlurmInteractionError::UnknownJob(7))
+        ));
+        manager.set_binary_path(fake_slurm_dir(&[(
+            "scontrol",
+            "echo 'scontrol: error: Invalid job id specified' >&2; exit 1",
+        )]));
+        manager.scheduled_jobs.push(submitted_job(3, 0));
+        match manager.release_job(3) {
+            Err(SlurmInteractionError::BadScontrolResponse(why)) => {
+                assert!(why.contains("Invalid job id"))
+            }
+            other => panic!("expected BadScontrolResponse, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn drain_stops_submitting_but_finishes_scheduled_jobs() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        manager.tick();
+        manager.drain();
+        assert!(!manager.is_drained());
+        mock.finish(1, "COMPLETED", "0:0");
+        let result = manager.tick();
+        assert_eq!((result.finished, result.submitted), (1, 0));
+        assert!(!result.work_remaining());
+        assert!(manager.is_drained());
+        assert_eq!(manager.open_jobs.len(), 1);
+        assert_eq!(mock.submitted_scripts().len(), 1);
+    }
+
+    #[test]
+    fn max_total_submissions_caps_submissions_over_all_ticks() {
+        let (mut manager, mock) = mock_manager(2);
+        manager.set_max_total_submissions(3);
+        manager.add_jobs((0..5).map(|_| sleep_job(None)).collect());
+        assert_eq!(manager.tick().submitted, 2);
+        mock.finish(1, "COMPLETED", "0:0");
+        mock.finish(2, "COMPLETED", "0:0");
+        assert_eq!(manager.tick().submitted, 1);
+        mock.finish(3, "COMPLETED", "0:0");
+        let result = manager.tick();
+        assert_eq!((result.finished, result.submitted), (1, 0));
+        assert_eq!(result.held_back, 2);
+        assert!(!result.work_remaining());
+        assert_eq!(manager.open_jobs.len(), 2);
+        assert_eq!(mock.submitted_scripts().len(), 3);
+    }
+
+    #[test]
+    fn submit_now_counts_towards_max_total_submissions() {
+        let (mut manager, mock) = mock_manager(5);
+        manager.set_max_total_submissions(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        manager.add_job(&sleep_job(None));
+        assert_eq!(manager.fill_up_queue().expect("nothing to submit"), 0);
+        manager.submit_now(&sleep_job(None)).expect("never refused");
+        assert_eq!(mock.submitted_scripts().len(), 2);
+    }
+
+    #[test]
+    fn submission_limit_reached_by_submit_now_is_warned_about_once() {
+        let (mut manager, _mock) = mock_manager(5);
+        manager.set_max_total_submissions(1);
+        manager.submit_now(&sleep_job(None)).expect("submitted");
+        assert!(!manager.submission_limit_warned);
+        manager.add_job(&sleep_job(None));
+        assert_eq!(manager.tick().held_back, 1);
+        assert!(manager.submission_limit_warned);
+        assert_eq!(manager.tick().held_back, 1);
+        manager.set_max_total_submissions(2);
+        assert!(!manager.submission_limit_warned);
+    }
+
+    #[test]
+    fn submission_limit_reached_at_a_tick_boundary_is_warned_about() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.set_max_total_submissions(1);
+        manager.add_job(&sleep_job(None));
+        assert_eq!(manager.tick().submitted, 1);
+        assert!(!manager.submission_limit_warned);
+        manager.add_job(&sleep_job(None));
+        mock.finish(1, "COMPLETED", "0:0");
+        let result = manager.tick();
+        assert_eq!((result.submitted, result.held_back), (0, 1));
+        assert!(manager.submission_limit_warned);
+    }
+
+    #[test]
+    fn nothing_is_held_back_below_the_submission_limit() {
+        let (mut manager, _mock) = mock_manager(1);
+        manager.set_max_total_submissions(5);
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        let result = manager.tick();
+        assert_eq!((result.submitted, result.held_back), (1, 0));
+        assert!(!manager.submission_limit_warned);
+    }
+
+    #[test]
+    fn failed_submissions_do_not_count_towards_max_total_submissions() {
         let mut manager = SlurmManager::new(1);
-        manager.add_job(&job);
-        manager.manage_jobs(Some(30));
-        assert!(
-            !marker_exists(&marker),
-            "job killed by the SLURM time limit should never reach the `touch` command"
+        manager.set_max_total_submissions(1);
+        manager.set_binary_path(fake_slurm_dir(&[("sbatch", "exit 1")]));
+        manager.add_jobs(vec![sleep_job(None), sleep_job(None)]);
+        assert!(manager.fill_up_queue().is_err());
+        // the first job waits for its retry backoff, the second one is next
+        manager.set_binary_path(fake_sbatch_dir());
+        assert_eq!(manager.fill_up_queue().expect("submitted"), 1);
+    }
+
+    #[test]
+    fn manage_jobs_returns_rejected_submissions() {
+        let (mut manager, mock) = mock_manager(1);
+        manager.set_max_retries(0);
+        mock.reject_submissions(1);
+        manager.add_job(&sleep_job(None));
+        match manager.manage_jobs(Some(5)) {
+            Err(errors) => {
+                assert_eq!(errors.len(), 1);
+                assert!(matches!(
+                    errors[0],
+                    SlurmInteractionError::BadSbatchResponse { .. }
+                ));
+                assert!(!errors[0].is_transient());
+            }
+            other => panic!("expected the sbatch error, got {:?}", other),
+        }
+        assert_eq!(manager.failed_jobs().len(), 1);
+    }
+
+    #[test]
+    fn manage_jobs_returns_errors_of_every_tick() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[(
+            "squeue",
+            "echo 'Unable to contact slurm controller' >&2; exit 1",
+        )]));
+        manager.set_poll_interval(Duration::from_millis(10));
+        manager.scheduled_jobs.push(submitted_job(4, 0));
+        let errors = manager.manage_jobs(Some(1)).expect_err("squeue failed");
+        assert!(errors.len() > 1);
+        assert!(errors.iter().all(SlurmInteractionError::is_transient));
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+    }
+
+    #[test]
+    fn manage_jobs_returns_once_drained() {
+        let (mut manager, _mock) = mock_manager(1);
+        manager.add_job(&sleep_job(None));
+        manager.drain();
+        assert!(manager.is_drained());
+        assert_eq!(manager.manage_jobs(Some(5)), Ok(true));
+        assert_eq!(manager.open_jobs.len(), 1);
+    }
+
+    #[test]
+    fn is_drained_is_false_without_drain() {
+        let manager = SlurmManager::new(1);
+        assert!(!manager.is_drained());
+    }
+
+    fn submitted_job(number: i32, submitted_secs_ago: i64) -> SlurmJob {
+        let mut job = sleep_job(None);
+        job.set_number(number);
+        job.set_status(QUEUED);
+        job.submitted_at = Some(Local::now() - TimeDelta::seconds(submitted_secs_ago));
+        job
+    }
+
+    #[test]
+    fn job_timeout_cancels_jobs_stuck_in_the_queue() {
+        let mut manager = SlurmManager::new(2);
+        manager.set_binary_path(fake_slurm_dir(&[("scancel", "exit 0")]));
+        manager.set_job_timeout(Duration::from_secs(60));
+        manager.scheduled_jobs.push(submitted_job(1, 120));
+        manager.scheduled_jobs.push(submitted_job(2, 10));
+        assert_eq!(manager.cancel_timed_out_jobs(), 1);
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+        assert_eq!(manager.scheduled_jobs[0].get_number(), 2);
+        assert_eq!(manager.finished_jobs[0].status(), TIMEOUT);
+        assert_eq!(manager.failed_jobs().len(), 1);
+    }
+
+    #[test]
+    fn job_timeout_is_disabled_by_default() {
+        let mut manager = SlurmManager::new(1);
+        manager
+            .scheduled_jobs
+            .push(submitted_job(1, 365 * 24 * 60 * 60));
+        assert_eq!(manager.cancel_timed_out_jobs(), 0);
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+    }
+
+    #[test]
+    fn job_timeout_keeps_jobs_that_could_not_be_cancelled() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_slurm_dir(&[("scancel", "exit 1")]));
+        manager.set_job_timeout(Duration::from_secs(1));
+        manager.scheduled_jobs.push(submitted_job(1, 120));
+        assert_eq!(manager.cancel_timed_out_jobs(), 0);
+        assert_eq!(manager.scheduled_jobs[0].status(), QUEUED);
+    }
+
+    #[test]
+    fn job_timeout_too_large_for_a_date_never_cancels() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_job_timeout(Duration::MAX);
+        manager.scheduled_jobs.push(submitted_job(1, 120));
+        assert_eq!(manager.cancel_timed_out_jobs(), 0);
+        // representable as a TimeDelta, but not as a date that far back
+        manager.set_job_timeout(Duration::from_secs(1 << 50));
+        assert_eq!(manager.cancel_timed_out_jobs(), 0);
+        assert_eq!(manager.scheduled_jobs.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "job timeout must not be zero")]
+    fn set_job_timeout_rejects_zero() {
+        SlurmManager::new(1).set_job_timeout(Duration::ZERO);
+    }
+
+    fn script_path(job: &SlurmJob) -> PathBuf {
+        SlurmManager::script_dir().join(format!("{}.slurm", job.get_id()))
+    }
+
+    #[test]
+    fn write_slurm_script_writes_the_given_script() {
+        let job = sleep_job(None);
+        let dir = std::env::temp_dir();
+        let script = job.generate_slurm_script();
+        let path = SlurmManager::write_slurm_script(&dir, &job, &script, DEFAULT_SCRIPT_MODE)
+            .expect("script written");
+        assert_eq!(path, dir.join(format!("{}.slurm", job.get_id())));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), script);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn write_slurm_script_overwrites_a_previous_attempt() {
+        let job = sleep_job(None);
+        let dir = std::env::temp_dir();
+        std::fs::write(dir.join(format!("{}.slurm", job.get_id())), "stale").unwrap();
+        let path = SlurmManager::write_slurm_script(&dir, &job, "", DEFAULT_SCRIPT_MODE)
+            .expect("script written");
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("stale"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn permissions(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn write_slurm_script_is_private_by_default() {
+        let dir = std::env::temp_dir();
+        let job = sleep_job(None);
+        std::fs::write(dir.join(format!("{}.slurm", job.get_id())), "stale").unwrap();
+        let path = SlurmManager::write_slurm_script(&dir, &job, "", DEFAULT_SCRIPT_MODE)
+            .expect("script written");
+        assert_eq!(permissions(&path), 0o600);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn keep_scripts_uses_configured_mode() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_sbatch_dir());
+        manager.set_keep_scripts(true);
+        manager.set_script_mode(0o640);
+        let mut job = sleep_job(None);
+        manager.schedule_job(&mut job).expect("job submitted");
+        assert_eq!(permissions(&script_path(&job)), 0o640);
+        let _ = std::fs::remove_file(script_path(&job));
+    }
+
+    #[test]
+    #[should_panic(expected = "script mode must be at most 0o777")]
+    fn set_script_mode_rejects_special_bits() {
+        SlurmManager::new(1).set_script_mode(0o4755);
+    }
+
+    #[test]
+    fn write_slurm_script_creates_missing_directory() {
+        let root = std::env::temp_dir().join(format!("slurm_scripts_{}", uuid::Uuid::new_v4()));
+        let dir = root.join("nested").join("tmp");
+        let path =
+            SlurmManager::write_slurm_script(&dir, &sleep_job(None), "", DEFAULT_SCRIPT_MODE)
+                .expect("script written");
+        assert!(path.starts_with(&dir) && path.exists());
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn write_slurm_script_joins_directory_without_trailing_slash() {
+        let job = sleep_job(None);
+        let dir = PathBuf::from(
+            std::env::temp_dir()
+                .display()
+                .to_string()
+                .trim_end_matches('/'),
         );
+        let path = SlurmManager::write_slurm_script(&dir, &job, "", DEFAULT_SCRIPT_MODE)
+            .expect("script written");
         assert_eq!(
-            manager.successful_jobs(),
-            0,
-            "a job killed by the SLURM time limit must not be counted as successful"
+            path,
+            std::env::temp_dir().join(format!("{}.slurm", job.get_id()))
         );
-        let _ = std::fs::remove_file(&marker);
+        let _ = std::fs::remove_file(path);
     }
 
     #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster with memory-limit enforcement (cgroups) enabled (run with --include-ignored)"]
-    fn memory_limit_kills_job() {
-        // allocate 200MB of tmpfs-backed memory against a 50MB SLURM cap
-        let marker = marker_path();
-        let bigfile = format!("/dev/shm/slurm_test_bigfile_{}", uuid::Uuid::new_v4());
-        let _ = std::fs::remove_file(&marker);
-        let _ = std::fs::remove_file(&bigfile);
-        let command = format!(
-            "dd if=/dev/zero of={} bs=1M count=200 && touch {} && rm -f {}",
-            bigfile, marker, bigfile
+    fn write_slurm_script_reports_unusable_directory() {
+        // a regular file where the directory should be cannot be created
+        let file = std::env::temp_dir().join(format!("slurm_not_a_dir_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&file, "").unwrap();
+        let dir = file.join("tmp");
+        match SlurmManager::write_slurm_script(&dir, &sleep_job(None), "", DEFAULT_SCRIPT_MODE) {
+            Err(SlurmInteractionError::ScriptNotWritten(why)) => {
+                assert!(why.starts_with(&dir.display().to_string()))
+            }
+            other => panic!("expected ScriptNotWritten, got {:?}", other),
+        }
+        let _ = std::fs::remove_file(file);
+    }
+
+    #[test]
+    fn schedule_job_writes_distinct_scripts() {
+        let mut manager = SlurmManager::new(2);
+        manager.set_binary_path(fake_sbatch_dir());
+        manager.set_keep_scripts(true);
+        let mut first = sleep_job(None);
+        let mut second = SlurmJobBuilder::new(String::from("sleep 10")).build();
+        assert_eq!(manager.schedule_job(&mut first).expect("submitted"), 42);
+        assert_eq!(manager.schedule_job(&mut second).expect("submitted"), 42);
+        let (first_script, second_script) = (script_path(&first), script_path(&second));
+        assert_ne!(first_script, second_script);
+        assert!(
+            std::fs::read_to_string(&first_script)
+                .unwrap()
+                .contains("sleep 5")
         );
-        let job = SlurmJobBuilder::new(command)
-            .set_memory(crate::memory_size::Memory::MegaByte(50))
-            .set_on_finished(marker_post_processing(&marker))
-            .build();
-        let mut manager = SlurmManager::new(1);
-        manager.add_job(&job);
-        manager.manage_jobs(Some(60));
         assert!(
-            !marker_exists(&marker),
-            "job exceeding its memory limit should be OOM-killed before writing the marker"
+            std::fs::read_to_string(&second_script)
+                .unwrap()
+                .contains("sleep 10")
         );
+        let _ = std::fs::remove_file(first_script);
+        let _ = std::fs::remove_file(second_script);
+    }
+
+    #[test]
+    fn schedule_job_keeps_no_script_by_default() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(fake_sbatch_dir());
+        let mut job = sleep_job(None);
+        manager.schedule_job(&mut job).expect("submitted");
+        assert!(!script_path(&job).exists());
+    }
+
+    #[test]
+    fn schedule_job_keeps_no_script_when_sbatch_is_missing() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_binary_path(PathBuf::from("/nonexistent/slurm/bin"));
+        let mut job = sleep_job(None);
+        assert!(manager.schedule_job(&mut job).is_err());
+        assert!(!script_path(&job).exists());
+    }
+
+    fn record_transitions(
+        manager: &mut SlurmManager,
+    ) -> std::sync::Arc<std::sync::Mutex<Vec<SlurmJobStatus>>> {
+        let transitions = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = transitions.clone();
+        manager.set_on_status_change(move |_, status| recorder.lock().unwrap().push(status));
+        transitions
+    }
+
+    #[test]
+    fn status_change_fires_when_job_is_added() {
+        let mut manager = SlurmManager::new(1);
+        let transitions = record_transitions(&mut manager);
+        manager.add_job(&sleep_job(None));
+        assert_eq!(*transitions.lock().unwrap(), vec![PENDING]);
+    }
+
+    #[test]
+    fn status_change_receives_job_and_new_status() {
+        let mut manager = SlurmManager::new(1);
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = seen.clone();
+        manager.set_on_status_change(move |job, status| {
+            assert_eq!(job.status(), status);
+            recorder
+                .lock()
+                .unwrap()
+                .push((job.command().to_string(), status));
+        });
+        manager.add_job(&sleep_job(None));
+        manager.cancel_all().expect("nothing was submitted");
         assert_eq!(
-            manager.successful_jobs(),
-            0,
-            "an OOM-killed job must not be counted as successful"
+            *seen.lock().unwrap(),
+            vec![
+                ("sleep 5".to_string(), PENDING),
+                ("sleep 5".to_string(), CANCELLED)
+            ]
         );
-        let _ = std::fs::remove_file(&marker);
-        let _ = std::fs::remove_file(&bigfile);
     }
 
     #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn queue_cap_never_exceeded() {
-        let max_queue = 2;
-        let mut manager = SlurmManager::new(max_queue);
-        for _ in 0..6 {
-            manager.add_job(&sleep_job(None));
-        }
-        let end_time = Local::now() + TimeDelta::seconds(60);
-        loop {
-            manager.check_on_jobs().expect("check on jobs");
-            manager.fill_up_queue().expect("fill up queue");
-            let running = manager.get_running_jobs().expect("get running jobs").len() as i32;
-            assert!(
-                running <= max_queue,
-                "queue cap of {} exceeded: {} jobs running",
-                max_queue,
-                running
-            );
-            if manager.open_jobs.is_empty() && manager.scheduled_jobs.is_empty() {
-                break;
-            }
-            assert!(
-                Local::now() < end_time,
-                "jobs did not complete within the test budget"
-            );
-            thread::sleep(Duration::from_secs(2));
-        }
+    fn status_change_fires_for_failed_submission() {
+        let mut manager = SlurmManager::new(1);
+        manager.set_max_retries(0);
+        let transitions = record_transitions(&mut manager);
+        manager.handle_failed_submission(sleep_job(None));
+        assert_eq!(*transitions.lock().unwrap(), vec![FAILED]);
+    }
+
+    fn state_path() -> PathBuf {
+        std::env::temp_dir().join(format!("slurm_manager_state_{}.json", uuid::Uuid::new_v4()))
     }
 
     #[test]
-    #[serial]
-    #[ignore = "requires a live SLURM cluster (run with --include-ignored)"]
-    fn crashed_job_not_counted_as_successful() {
-        let always_fail = SlurmJobPostProcessing::new(&[], |_| false);
-        let job = SlurmJobBuilder::new(String::from("sleep 5"))
-            .set_on_finished(always_fail)
+    fn save_and_load_state_round_trips_jobs() {
+        let path = state_path();
+        let mut manager = SlurmManager::new(4);
+        let open = manager.add_job(&SlurmJobBuilder::new("echo open".to_string()).build());
+        let submitted = manager.add_job(&sleep_job(None));
+        let mut job = manager.open_jobs.pop_back().expect("submitted job");
+        job.set_number(321);
+        job.set_status(RUNNING);
+        manager.scheduled_jobs.push(job);
+        manager.save_state(&path).expect("save state");
+
+        let mut restored =
+            SlurmManager::load_state(&path, |_| SlurmJobPostProcessing::do_nothing())
+                .expect("load state");
+        assert_eq!(restored.max_queue, 4);
+        assert_eq!(restored.open_jobs.len(), 1);
+        assert_eq!(restored.open_jobs[0].command(), "echo open");
+        assert_eq!(restored.job_number(submitted), Some(321));
+        assert_eq!(restored.job_number(open), None);
+        assert_eq!(restored.scheduled_jobs[0].status(), RUNNING);
+        let next = restored.add_job(&sleep_job(None));
+        assert_ne!(next, open);
+        assert_ne!(next, submitted);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_state_attaches_post_processing_by_job_id() {
+        let path = state_path();
+        let mut manager = SlurmManager::new(1);
+        let checked = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_id("checked".to_string())
             .build();
+        manager.add_jobs(vec![checked, sleep_job(None)]);
+        manager.save_state(&path).expect("save state");
+        let restored = SlurmManager::load_state(&path, |id| match id {
+            "checked" => SlurmJobPostProcessing::new(&[], |_| false),
+            _ => SlurmJobPostProcessing::do_nothing(),
+        })
+        .expect("load state");
+        assert_eq!(restored.open_jobs[0].run_post_processing(), CRASHED);
+        assert_eq!(restored.open_jobs[1].run_post_processing(), FINISHED);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn post_processing_can_be_replaced_after_loading() {
+        let path = state_path();
         let mut manager = SlurmManager::new(1);
-        manager.add_job(&job);
-        manager.manage_jobs(Some(15));
-        assert_eq!(
-            manager.successful_jobs(),
-            0,
-            "a job whose post-processing fails should not be counted as successful"
-        );
+        let handle = manager.add_job(&sleep_job(None));
+        manager.save_state(&path).expect("save state");
+        let mut restored =
+            SlurmManager::load_state(&path, |_| SlurmJobPostProcessing::do_nothing())
+                .expect("load state");
+        restored
+            .set_on_finished(handle, SlurmJobPostProcessing::new(&[], |_| false))
+            .expect("known handle");
+        assert_eq!(restored.open_jobs[0].run_post_processing(), CRASHED);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn set_on_finished_replaces_check_of_scheduled_job() {
+        let (mut manager, _mock) = mock_manager(1);
+        let handle = manager.add_job(&sleep_job(None));
+        manager.fill_up_queue().expect("submitted");
+        manager
+            .set_on_finished(handle, SlurmJobPostProcessing::new(&[], |_| false))
+            .expect("known handle");
+        assert_eq!(manager.scheduled_jobs[0].run_post_processing(), CRASHED);
+    }
+
+    #[test]
+    fn set_on_finished_rejects_unknown_handle() {
+        let mut manager = SlurmManager::new(1);
+        manager.add_job(&sleep_job(None));
+        match manager.set_on_finished(JobHandle(42), SlurmJobPostProcessing::do_nothing()) {
+            Err(SlurmInteractionError::UnknownHandle(JobHandle(42))) => {}
+            other => panic!("expected UnknownHandle(JobHandle(42)), got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_state_keeps_counting_towards_max_total_submissions() {
+        let path = state_path();
+        let (mut manager, _mock) = mock_manager(10);
+        manager.add_jobs((0..5).map(|_| sleep_job(None)).collect());
+        manager.set_max_total_submissions(2);
+        assert_eq!(manager.fill_up_queue().expect("submitted"), 2);
+        manager.save_state(&path).expect("save state");
+        let mut restored =
+            SlurmManager::load_state(&path, |_| SlurmJobPostProcessing::do_nothing())
+                .expect("load state");
+        let mock = MockScheduler::new();
+        restored.set_backend(mock.clone());
+        restored.set_max_total_submissions(4);
+        assert_eq!(restored.fill_up_queue().expect("submitted"), 2);
+        assert_eq!(mock.submitted_scripts().len(), 2);
+        assert_eq!(restored.open_jobs.len(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_state_without_total_submissions_counts_from_zero() {
+        let path = state_path();
+        std::fs::write(
+            &path,
+            r#"{"max_queue": 2, "next_handle": 0, "open_jobs": [],
+                "scheduled_jobs": [], "finished_jobs": []}"#,
+        )
+        .unwrap();
+        let restored = SlurmManager::load_state(&path, |_| SlurmJobPostProcessing::do_nothing())
+            .expect("load state");
+        assert_eq!(restored.total_submissions, 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_state_reports_missing_and_corrupt_files() {
+        let path = state_path();
+        let do_nothing = |_: &str| SlurmJobPostProcessing::do_nothing();
+        assert!(SlurmManager::load_state(&path, do_nothing).is_err());
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(SlurmManager::load_state(&path, do_nothing).is_err());
+        let _ = std::fs::remove_file(&path);
     }
 
     #[test]
     fn post_processing_check_returns_false_on_failure() {
         let failing = SlurmJobPostProcessing::new(&[], |_| false);
+        let context = sleep_job(None).post_processing_context();
         assert!(
-            !failing.check(),
+            !failing.check(&context),
             "post-processing returning false should propagate as false"
         );
     }
@@ -500,6 +4883,143 @@ popd
     #[test]
     fn post_processing_check_returns_true_on_success() {
         let succeeding = SlurmJobPostProcessing::new(&[], |_| true);
-        assert!(succeeding.check());
+        assert!(succeeding.check(&sleep_job(None).post_processing_context()));
+    }
+
+    #[test]
+    fn post_processing_closure_can_capture_state() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let calls = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let counting = SlurmJobPostProcessing::new(&[], move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            true
+        });
+        let job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_on_finished(counting)
+            .build();
+        let copy = job.clone();
+        assert_eq!(job.run_post_processing(), FINISHED);
+        assert_eq!(copy.run_post_processing(), FINISHED);
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            2,
+            "clones share the captured state"
+        );
+    }
+
+    #[test]
+    fn post_processing_with_context_sees_exit_code() {
+        let exit_ok = SlurmJobPostProcessing::with_context(&[], |_, context| {
+            context.exit_code() == Some("0:0")
+        });
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_on_finished(exit_ok)
+            .build();
+        job.exit_code = Some("1:0".to_string());
+        assert_eq!(job.run_post_processing(), CRASHED);
+        job.exit_code = Some("0:0".to_string());
+        assert_eq!(job.run_post_processing(), FINISHED);
+    }
+
+    #[test]
+    fn post_processing_context_resolves_log_paths() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_file("/logs/%x-%j.out".to_string())
+            .set_error_file("err_%j_100%%.log".to_string())
+            .build();
+        job.set_number(77);
+        let context = job.post_processing_context();
+        assert_eq!(context.number(), Some(77));
+        assert_eq!(context.job_id(), job.get_id());
+        assert_eq!(
+            context.output_file(),
+            Some(&PathBuf::from(format!("/logs/{}-77.out", job.get_id())))
+        );
+        assert_eq!(
+            context.error_file(),
+            Some(&std::env::current_dir().unwrap().join("err_77_100%.log"))
+        );
+    }
+
+    #[test]
+    fn post_processing_context_resolves_log_paths_in_chdir_directory() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_working_directory("/scratch/run1".to_string())
+            .set_chdir(true)
+            .set_output_file("logs/%j.out".to_string())
+            .build();
+        job.set_number(77);
+        assert_eq!(
+            job.post_processing_context().output_file(),
+            Some(&PathBuf::from("/scratch/run1/logs/77.out"))
+        );
+    }
+
+    #[test]
+    fn post_processing_context_resolves_log_paths_in_submission_directory_with_pushd() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_working_directory("/scratch/run1".to_string())
+            .set_output_file("logs/%j.out".to_string())
+            .build();
+        job.set_number(77);
+        assert_eq!(
+            job.post_processing_context().output_file(),
+            Some(&std::env::current_dir().unwrap().join("logs/77.out"))
+        );
+    }
+
+    #[test]
+    fn post_processing_context_resolves_job_name() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_name("align".to_string())
+            .set_output_file("/logs/%x-%j.out".to_string())
+            .build();
+        job.set_number(78);
+        let context = job.post_processing_context();
+        assert_eq!(
+            context.output_file(),
+            Some(&PathBuf::from(format!("/logs/{}-78.out", job.job_name())))
+        );
+    }
+
+    #[test]
+    fn post_processing_context_resolves_padded_and_array_job_numbers() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_pattern("/logs/%6j.out".to_string())
+            .set_error_pattern("/logs/%A-%%.err".to_string())
+            .build();
+        job.set_number(78);
+        let context = job.post_processing_context();
+        assert_eq!(
+            context.output_file(),
+            Some(&PathBuf::from("/logs/000078.out"))
+        );
+        assert_eq!(context.error_file(), Some(&PathBuf::from("/logs/78-%.err")));
+    }
+
+    #[test]
+    fn post_processing_context_leaves_out_per_task_log_paths() {
+        let mut job = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_array("0-9".to_string())
+            .set_output_pattern("/logs/%A_%4a.out".to_string())
+            .set_error_pattern("/logs/%j.err".to_string())
+            .build();
+        job.set_number(78);
+        let context = job.post_processing_context();
+        assert_eq!(context.output_file(), None);
+        assert_eq!(context.error_file(), None);
+        let on_node = SlurmJobBuilder::new(String::from("sleep 5"))
+            .set_output_pattern("/logs/%N.out".to_string())
+            .build();
+        assert_eq!(on_node.post_processing_context().output_file(), None);
+    }
+
+    #[test]
+    fn post_processing_context_without_accounting() {
+        let context = sleep_job(None).post_processing_context();
+        assert_eq!(context.number(), None);
+        assert_eq!(context.exit_code(), None);
+        assert_eq!(context.output_file(), Some(&PathBuf::from("/dev/null")));
     }
 }
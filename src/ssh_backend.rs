@@ -0,0 +1,142 @@
+use crate::job::SlurmJob;
+use crate::slurm_backend::{
+    DEFAULT_COMMAND_TIMEOUT, FORMAT_OVERRIDES, SlurmBackend, output_with_input,
+};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::time::Duration;
+
+// Runs the SLURM binaries on a remote login node through `ssh`. Authentication
+// has to work without a prompt (e.g. an ssh key or agent), ssh runs with
+// BatchMode=yes so it fails instead of waiting for a password.
+#[derive(Clone, Debug)]
+pub struct SshBackend {
+    destination: String,
+    ssh_options: Vec<String>,
+    binary_path: Option<PathBuf>,
+    command_timeout: Duration,
+}
+
+impl SshBackend {
+    // destination as passed to ssh, e.g. "user@login.cluster.org" or a host
+    // alias from ~/.ssh/config
+    pub fn new(destination: String) -> SshBackend {
+        SshBackend {
+            destination,
+            ssh_options: Vec::new(),
+            binary_path: None,
+            command_timeout: DEFAULT_COMMAND_TIMEOUT,
+        }
+    }
+
+    // extra arguments for ssh itself, e.g. "-p" followed by "2222"
+    pub fn add_ssh_option(mut self, option: String) -> SshBackend {
+        self.ssh_options.push(option);
+        self
+    }
+
+    // directory containing the SLURM binaries on the remote host, by default
+    // they are looked up on the remote PATH
+    pub fn set_binary_path(mut self, binary_path: PathBuf) -> SshBackend {
+        self.binary_path = Some(binary_path);
+        self
+    }
+
+    // ssh hands the command to the remote shell, so every part is quoted. The
+    // remote login may set its own output formats, env drops them first.
+    fn remote_command(&self, program: &str, args: &[String]) -> String {
+        let program = match self.binary_path {
+            Some(ref binary_path) => binary_path.join(program).to_string_lossy().to_string(),
+            None => program.to_string(),
+        };
+        let unset: Vec<String> = FORMAT_OVERRIDES
+            .iter()
+            .map(|variable| format!("-u {} ", variable))
+            .collect();
+        let quoted: Vec<String> = std::iter::once(&program)
+            .chain(args)
+            .map(|part| SlurmJob::shell_quote(part))
+            .collect();
+        format!("env {}{}", unset.concat(), quoted.join(" "))
+    }
+
+    fn command(&self, program: &str, args: &[String]) -> Command {
+        let mut command = Command::new("ssh");
+        command
+            .args(["-o", "BatchMode=yes"])
+            .args(&self.ssh_options)
+            .arg(&self.destination)
+            .arg("--")
+            .arg(self.remote_command(program, args));
+        command
+    }
+}
+
+impl SlurmBackend for SshBackend {
+    fn run(&self, program: &str, args: &[String], input: Option<&str>) -> std::io::Result<Output> {
+        output_with_input(self.command(program, args), input, self.command_timeout)
+    }
+
+    // covers the ssh connection as well as the remote command
+    fn set_command_timeout(&mut self, timeout: Duration) {
+        self.command_timeout = timeout;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn command_runs_ssh_with_quoted_remote_command() {
+        let backend = SshBackend::new("me@login.cluster.org".to_string());
+        let command = backend.command("squeue", &["--format".to_string(), "%.i %.t".to_string()]);
+        assert_eq!(command.get_program(), "ssh");
+        assert_eq!(
+            args(&command),
+            [
+                "-o",
+                "BatchMode=yes",
+                "me@login.cluster.org",
+                "--",
+                "env -u SQUEUE_FORMAT -u SQUEUE_FORMAT2 -u SACCT_FORMAT 'squeue' '--format' '%.i %.t'"
+            ]
+        );
+    }
+
+    #[test]
+    fn command_uses_options_and_remote_binary_path() {
+        let backend = SshBackend::new("cluster".to_string())
+            .add_ssh_option("-p".to_string())
+            .add_ssh_option("2222".to_string())
+            .set_binary_path(PathBuf::from("/opt/slurm/bin"));
+        assert_eq!(
+            args(&backend.command("scancel", &["42".to_string()])),
+            [
+                "-o",
+                "BatchMode=yes",
+                "-p",
+                "2222",
+                "cluster",
+                "--",
+                "env -u SQUEUE_FORMAT -u SQUEUE_FORMAT2 -u SACCT_FORMAT '/opt/slurm/bin/scancel' '42'"
+            ]
+        );
+    }
+
+    #[test]
+    fn remote_command_escapes_single_quotes() {
+        let backend = SshBackend::new("cluster".to_string());
+        assert_eq!(
+            backend.remote_command("sacct", &["it's".to_string()]),
+            "env -u SQUEUE_FORMAT -u SQUEUE_FORMAT2 -u SACCT_FORMAT 'sacct' 'it'\\''s'"
+        );
+    }
+}
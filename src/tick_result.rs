@@ -0,0 +1,44 @@
+use crate::slurm_manager::SlurmInteractionError;
+
+// Outcome of one SlurmManager::tick, i.e. one check on the queue followed by
+// submitting new jobs. Errors are logged by the manager, returned in `errors`
+// and retried on the next tick.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TickResult {
+    pub finished: i32,
+    pub submitted: i32,
+    pub remaining: usize,
+    // open jobs that will not be submitted because max_total_submissions was
+    // reached, they are not part of remaining
+    pub held_back: usize,
+    pub errors: Vec<SlurmInteractionError>,
+}
+
+impl TickResult {
+    // false once every job is finished, there is no need to tick again then
+    pub fn work_remaining(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn work_remains_while_jobs_are_left() {
+        let result = TickResult {
+            finished: 1,
+            submitted: 2,
+            remaining: 3,
+            held_back: 0,
+            errors: Vec::new(),
+        };
+        assert!(result.work_remaining());
+    }
+
+    #[test]
+    fn no_work_remains_without_jobs() {
+        assert!(!TickResult::default().work_remaining());
+    }
+}